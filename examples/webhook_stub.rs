@@ -0,0 +1,92 @@
+// Local receiver for trying out webhooks.
+//
+//   WEBHOOK_SECRET=whsec_... FAIL_FIRST=2 cargo run --example webhook_stub
+//
+// Register http://127.0.0.1:4000/webhook as a webhook URL. Every delivery is
+// printed after its signature is checked against `WEBHOOK_SECRET`. The first
+// `FAIL_FIRST` deliveries are answered with a 500 to exercise retries.
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+struct Stub {
+    secret: Option<String>,
+    fail_first: u32,
+    received: AtomicU32,
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or("")
+}
+
+fn verify(secret: &str, headers: &HeaderMap, body: &str) -> bool {
+    let Some(signature) = header(headers, "x-webhook-signature").strip_prefix("sha256=") else {
+        return false;
+    };
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(header(headers, "x-webhook-timestamp").as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+async fn receive(State(stub): State<Arc<Stub>>, headers: HeaderMap, body: String) -> StatusCode {
+    let count = stub.received.fetch_add(1, Ordering::SeqCst) + 1;
+    println!(
+        "#{} {} (event id {})",
+        count,
+        header(&headers, "x-webhook-event"),
+        header(&headers, "x-webhook-id")
+    );
+
+    if let Some(secret) = &stub.secret {
+        if !verify(secret, &headers, &body) {
+            println!("   signature mismatch, rejected");
+            return StatusCode::UNAUTHORIZED;
+        }
+        println!("   signature ok");
+    }
+    println!("   {}", body);
+
+    if count <= stub.fail_first {
+        println!("   failing on purpose");
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+
+    StatusCode::NO_CONTENT
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let stub = Arc::new(Stub {
+        secret: std::env::var("WEBHOOK_SECRET").ok(),
+        fail_first: std::env::var("FAIL_FIRST").ok().and_then(|n| n.parse().ok()).unwrap_or(0),
+        received: AtomicU32::new(0),
+    });
+    if stub.secret.is_none() {
+        println!("WEBHOOK_SECRET is not set, signatures are not checked");
+    }
+
+    let app = Router::new().route("/webhook", post(receive)).with_state(stub);
+
+    let addr = std::env::var("STUB_ADDR").unwrap_or_else(|_| "127.0.0.1:4000".to_string());
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    println!("Listening on http://{}/webhook", addr);
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
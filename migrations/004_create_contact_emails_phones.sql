@@ -0,0 +1,25 @@
+-- Create contact_emails and contact_phones tables
+CREATE TABLE IF NOT EXISTS contact_emails (
+    id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    contact_id INTEGER NOT NULL,
+    email VARCHAR(200) NOT NULL,
+    label VARCHAR(10) NOT NULL DEFAULT 'other',
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY (contact_id) REFERENCES contacts(id) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+CREATE TABLE IF NOT EXISTS contact_phones (
+    id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    contact_id INTEGER NOT NULL,
+    phone VARCHAR(20) NOT NULL,
+    label VARCHAR(10) NOT NULL DEFAULT 'other',
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY (contact_id) REFERENCES contacts(id) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- Existing single email/phone values become the primary entries
+INSERT INTO contact_emails (contact_id, email, label, is_primary)
+SELECT id, email, 'other', TRUE FROM contacts WHERE email IS NOT NULL;
+
+INSERT INTO contact_phones (contact_id, phone, label, is_primary)
+SELECT id, phone, 'other', TRUE FROM contacts WHERE phone IS NOT NULL;
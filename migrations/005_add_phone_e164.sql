@@ -0,0 +1,7 @@
+-- Store the normalized E.164 form next to the phone as entered
+ALTER TABLE contacts
+    ADD COLUMN phone_e164 VARCHAR(20) NULL AFTER phone;
+
+ALTER TABLE contact_phones
+    ADD COLUMN e164 VARCHAR(20) NULL AFTER phone,
+    ADD INDEX idx_contact_phones_e164 (e164);
@@ -0,0 +1,35 @@
+-- Create address_books and address_book_members tables
+CREATE TABLE IF NOT EXISTS address_books (
+    id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    username VARCHAR(100) NOT NULL,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY (username) REFERENCES users(username) ON DELETE RESTRICT ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+CREATE TABLE IF NOT EXISTS address_book_members (
+    address_book_id INTEGER NOT NULL,
+    username VARCHAR(100) NOT NULL,
+    role VARCHAR(10) NOT NULL,
+    PRIMARY KEY (address_book_id, username),
+    FOREIGN KEY (address_book_id) REFERENCES address_books(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (username) REFERENCES users(username) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- Every user that already has contacts gets a default book owning them
+INSERT INTO address_books (name, username, is_default)
+SELECT DISTINCT 'Contacts', username, TRUE FROM contacts;
+
+INSERT INTO address_book_members (address_book_id, username, role)
+SELECT id, username, 'owner' FROM address_books;
+
+ALTER TABLE contacts
+    ADD COLUMN address_book_id INTEGER NULL AFTER username;
+
+UPDATE contacts c
+JOIN address_books b ON b.username = c.username AND b.is_default = TRUE
+SET c.address_book_id = b.id;
+
+ALTER TABLE contacts
+    MODIFY address_book_id INTEGER NOT NULL,
+    ADD FOREIGN KEY (address_book_id) REFERENCES address_books(id) ON DELETE RESTRICT ON UPDATE CASCADE;
@@ -0,0 +1,20 @@
+-- Create custom_fields and contact_custom_values tables
+CREATE TABLE IF NOT EXISTS custom_fields (
+    id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(50) NOT NULL,
+    field_type VARCHAR(10) NOT NULL,
+    required BOOLEAN NOT NULL DEFAULT FALSE,
+    options TEXT NULL,
+    username VARCHAR(100) NOT NULL,
+    UNIQUE KEY uq_custom_fields_username_name (username, name),
+    FOREIGN KEY (username) REFERENCES users(username) ON DELETE RESTRICT ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+CREATE TABLE IF NOT EXISTS contact_custom_values (
+    contact_id INTEGER NOT NULL,
+    field_id INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (contact_id, field_id),
+    FOREIGN KEY (contact_id) REFERENCES contacts(id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (field_id) REFERENCES custom_fields(id) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
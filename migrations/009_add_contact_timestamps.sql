@@ -0,0 +1,6 @@
+-- Track when contacts are created and last changed, used for sorting
+ALTER TABLE contacts
+    ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    ADD INDEX idx_contacts_created_at (created_at),
+    ADD INDEX idx_contacts_updated_at (updated_at);
@@ -0,0 +1,6 @@
+-- Full-text indexes for the `q` contact search
+ALTER TABLE contacts
+    ADD FULLTEXT INDEX ft_contacts_search (first_name, last_name, email, phone);
+
+ALTER TABLE addresses
+    ADD FULLTEXT INDEX ft_addresses_search (street, city, province, country, postal_code);
@@ -0,0 +1,9 @@
+-- Create saved_searches table, filters hold a search request as JSON
+CREATE TABLE IF NOT EXISTS saved_searches (
+    id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    filters TEXT NOT NULL,
+    username VARCHAR(100) NOT NULL,
+    UNIQUE KEY uq_saved_searches_username_name (username, name),
+    FOREIGN KEY (username) REFERENCES users(username) ON DELETE RESTRICT ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -0,0 +1,4 @@
+-- Address type (home, work, billing, shipping, other) and primary flag
+ALTER TABLE addresses
+    ADD COLUMN address_type VARCHAR(20) NOT NULL DEFAULT 'other' AFTER postal_code,
+    ADD COLUMN is_primary BOOLEAN NOT NULL DEFAULT FALSE AFTER address_type;
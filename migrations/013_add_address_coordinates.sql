@@ -0,0 +1,5 @@
+-- Coordinates filled in by the geocoder after an address is saved
+ALTER TABLE addresses
+    ADD COLUMN latitude DOUBLE NULL AFTER postal_code,
+    ADD COLUMN longitude DOUBLE NULL AFTER latitude,
+    ADD INDEX idx_addresses_coordinates (latitude, longitude);
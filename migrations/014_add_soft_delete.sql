@@ -0,0 +1,8 @@
+-- Deleted contacts and addresses stay in the trash until they are purged
+ALTER TABLE contacts
+    ADD COLUMN deleted_at TIMESTAMP NULL,
+    ADD INDEX idx_contacts_deleted_at (deleted_at);
+
+ALTER TABLE addresses
+    ADD COLUMN deleted_at TIMESTAMP NULL,
+    ADD INDEX idx_addresses_deleted_at (deleted_at);
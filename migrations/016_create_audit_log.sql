@@ -0,0 +1,23 @@
+-- Every create, update and delete of contacts, addresses and users.
+-- Entries outlive the rows they describe, so there are no foreign keys.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    actor VARCHAR(100) NOT NULL,
+    entity VARCHAR(20) NOT NULL,
+    entity_id VARCHAR(100) NOT NULL,
+    -- Contact the entry belongs to, also set for its addresses
+    contact_id INTEGER NULL,
+    action VARCHAR(20) NOT NULL,
+    -- JSON with the changed fields before and after
+    changes TEXT NOT NULL,
+    request_id VARCHAR(64) NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_audit_log_entity (entity, entity_id),
+    INDEX idx_audit_log_contact (contact_id),
+    INDEX idx_audit_log_actor (actor),
+    INDEX idx_audit_log_created_at (created_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- Admins can query the whole audit log
+ALTER TABLE users
+    ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,14 @@
+-- Snapshots of a contact and its addresses after every change, numbered
+-- per contact
+CREATE TABLE IF NOT EXISTS contact_revisions (
+    id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    contact_id INTEGER NOT NULL,
+    revision INTEGER NOT NULL,
+    -- JSON snapshot, see `ContactSnapshot`
+    snapshot MEDIUMTEXT NOT NULL,
+    username VARCHAR(100) NOT NULL,
+    reverted_from INTEGER NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uq_contact_revisions_contact_revision (contact_id, revision),
+    FOREIGN KEY (contact_id) REFERENCES contacts(id) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
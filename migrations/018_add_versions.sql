@@ -0,0 +1,7 @@
+-- Bumped on every change so clients can detect concurrent edits through
+-- ETag / If-Match
+ALTER TABLE contacts
+    ADD COLUMN version INT NOT NULL DEFAULT 1;
+
+ALTER TABLE addresses
+    ADD COLUMN version INT NOT NULL DEFAULT 1;
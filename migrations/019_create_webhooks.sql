@@ -0,0 +1,48 @@
+-- Per-user webhook subscriptions. `events` is a comma separated list of
+-- event names, see `WebhookEvent`.
+CREATE TABLE IF NOT EXISTS webhooks (
+    id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    username VARCHAR(100) NOT NULL,
+    url VARCHAR(2048) NOT NULL,
+    events VARCHAR(500) NOT NULL,
+    -- Key for the HMAC-SHA256 signature of every delivery
+    secret VARCHAR(100) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (username) REFERENCES users(username) ON DELETE CASCADE ON UPDATE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- Events written in the transaction of the change that caused them and
+-- fanned out to matching webhooks by the dispatcher. Like the audit log,
+-- rows outlive the contacts they describe.
+CREATE TABLE IF NOT EXISTS webhook_outbox (
+    id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    event VARCHAR(50) NOT NULL,
+    contact_id INTEGER NOT NULL,
+    address_book_id INTEGER NOT NULL,
+    -- JSON of the contact or address after the change, before it for deletes
+    data MEDIUMTEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    dispatched_at TIMESTAMP NULL,
+    INDEX idx_webhook_outbox_dispatched_at (dispatched_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+-- One row per event and webhook, updated on every attempt
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    webhook_id INTEGER NOT NULL,
+    outbox_id BIGINT NOT NULL,
+    status ENUM('pending', 'delivered', 'failed') NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    response_status INTEGER NULL,
+    error VARCHAR(500) NULL,
+    delivered_at TIMESTAMP NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uq_webhook_deliveries_webhook_outbox (webhook_id, outbox_id),
+    INDEX idx_webhook_deliveries_due (status, next_attempt_at),
+    FOREIGN KEY (webhook_id) REFERENCES webhooks(id) ON DELETE CASCADE,
+    FOREIGN KEY (outbox_id) REFERENCES webhook_outbox(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
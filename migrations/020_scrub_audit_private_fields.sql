@@ -0,0 +1,18 @@
+-- Tags and custom field values are private to their owner but contact
+-- history is shared, so take them out of the entries already written.
+UPDATE audit_log
+SET changes = JSON_REMOVE(
+    changes,
+    '$.before.tags',
+    '$.after.tags',
+    '$.before.custom_fields',
+    '$.after.custom_fields'
+)
+WHERE entity = 'contact';
+
+-- Updates that only touched tags have nothing left to show
+DELETE FROM audit_log
+WHERE entity = 'contact'
+    AND action = 'update'
+    AND JSON_LENGTH(changes, '$.before') = 0
+    AND JSON_LENGTH(changes, '$.after') = 0;
@@ -0,0 +1,21 @@
+-- Default books are now created with the user instead of on first use.
+-- Extra default books left by concurrent first requests become regular
+-- books, and users that never needed one get theirs here.
+UPDATE address_books b
+JOIN (
+    SELECT username, MIN(id) AS id FROM address_books WHERE is_default = TRUE GROUP BY username
+) d ON d.username = b.username
+SET b.is_default = FALSE
+WHERE b.is_default = TRUE AND b.id <> d.id;
+
+INSERT INTO address_books (name, username, is_default)
+SELECT 'Contacts', u.username, TRUE FROM users u
+WHERE NOT EXISTS (
+    SELECT 1 FROM address_books b WHERE b.username = u.username AND b.is_default = TRUE
+);
+
+INSERT INTO address_book_members (address_book_id, username, role)
+SELECT b.id, b.username, 'owner' FROM address_books b
+WHERE b.is_default = TRUE AND NOT EXISTS (
+    SELECT 1 FROM address_book_members m WHERE m.address_book_id = b.id AND m.username = b.username
+);
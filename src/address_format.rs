@@ -0,0 +1,107 @@
+use crate::models::{AddressFormat, AddressResponse};
+
+// Layouts for printing addresses, one line per template line. Placeholders
+// without a value are dropped along with lines left empty.
+const DEFAULT_TEMPLATE: &str = "{street}\n{city} {province} {postal_code}\n{country}";
+
+const TEMPLATES: [(&str, &str); 32] = [
+    ("AR", "{street}\n{postal_code} {city}\n{province}\n{country}"),
+    ("AT", "{street}\n{postal_code} {city}\n{country}"),
+    ("AU", "{street}\n{city} {province} {postal_code}\n{country}"),
+    ("BE", "{street}\n{postal_code} {city}\n{country}"),
+    ("BR", "{street}\n{city} - {province}\n{postal_code}\n{country}"),
+    ("CA", "{street}\n{city} {province} {postal_code}\n{country}"),
+    ("CH", "{street}\n{postal_code} {city}\n{country}"),
+    ("CN", "{country}\n{province} {city}\n{street}\n{postal_code}"),
+    ("DE", "{street}\n{postal_code} {city}\n{country}"),
+    ("DK", "{street}\n{postal_code} {city}\n{country}"),
+    ("ES", "{street}\n{postal_code} {city}\n{province}\n{country}"),
+    ("FI", "{street}\n{postal_code} {city}\n{country}"),
+    ("FR", "{street}\n{postal_code} {city}\n{country}"),
+    ("GB", "{street}\n{city}\n{province}\n{postal_code}\n{country}"),
+    ("ID", "{street}\n{city}\n{province} {postal_code}\n{country}"),
+    ("IE", "{street}\n{city}\n{province}\n{postal_code}\n{country}"),
+    ("IN", "{street}\n{city} {postal_code}\n{province}\n{country}"),
+    ("IT", "{street}\n{postal_code} {city} {province}\n{country}"),
+    ("JP", "{country}\n{postal_code}\n{province} {city}\n{street}"),
+    ("KR", "{country}\n{province} {city}\n{street}\n{postal_code}"),
+    ("MX", "{street}\n{postal_code} {city}, {province}\n{country}"),
+    ("MY", "{street}\n{postal_code} {city}\n{province}\n{country}"),
+    ("NL", "{street}\n{postal_code} {city}\n{country}"),
+    ("NO", "{street}\n{postal_code} {city}\n{country}"),
+    ("NZ", "{street}\n{city} {postal_code}\n{country}"),
+    ("PH", "{street}\n{city}\n{postal_code} {province}\n{country}"),
+    ("PL", "{street}\n{postal_code} {city}\n{country}"),
+    ("PT", "{street}\n{postal_code} {city}\n{country}"),
+    ("SE", "{street}\n{postal_code} {city}\n{country}"),
+    ("SG", "{street}\n{country} {postal_code}"),
+    ("TH", "{street}\n{city}\n{province} {postal_code}\n{country}"),
+    ("US", "{street}\n{city}, {province} {postal_code}\n{country}"),
+];
+
+fn template(code: &str) -> &'static str {
+    TEMPLATES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or(DEFAULT_TEMPLATE, |(_, template)| template)
+}
+
+// Labels follow postal conventions: one line per part and the country name
+// in capitals. Single lines join the same parts with commas.
+pub fn format(address: &AddressResponse, style: AddressFormat) -> String {
+    let country = match (&address.country_name, style) {
+        (Some(name), AddressFormat::Label) => name.to_uppercase(),
+        (Some(name), AddressFormat::SingleLine) => name.clone(),
+        (None, _) => address.country.clone(),
+    };
+    let mut lines = Vec::new();
+    for line in template(&address.country).lines() {
+        let filled = fill(line, |placeholder| match placeholder {
+            "street" => address.street.as_deref().unwrap_or(""),
+            "city" => address.city.as_deref().unwrap_or(""),
+            "province" => address.province.as_deref().unwrap_or(""),
+            "postal_code" => &address.postal_code,
+            "country" => &country,
+            _ => "",
+        });
+        // Multi-line streets keep their own lines
+        lines.extend(filled.lines().map(tidy).filter(|l| !l.is_empty()));
+    }
+
+    match style {
+        AddressFormat::Label => lines.join("\n"),
+        AddressFormat::SingleLine => lines.join(", "),
+    }
+}
+
+// Replaces `{name}` placeholders in one pass, so braces in the values are
+// left alone
+fn fill<'a>(template: &str, value: impl Fn(&str) -> &'a str) -> String {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        filled.push_str(&rest[..start]);
+        filled.push_str(value(&rest[start + 1..start + end]));
+        rest = &rest[start + end + 1..];
+    }
+    filled.push_str(rest);
+
+    filled
+}
+
+// Cleans up the separators left around empty placeholders
+fn tidy(line: &str) -> String {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let mut tidied = words.join(" ").replace(" ,", ",");
+    while tidied.contains(",,") {
+        tidied = tidied.replace(",,", ",");
+    }
+
+    tidied
+        .trim_matches(|c: char| c == ',' || c == '-' || c.is_whitespace())
+        .to_string()
+}
+
@@ -0,0 +1,49 @@
+use phonenumber::country;
+
+#[derive(Clone)]
+pub struct Config {
+    // Region used to parse phone numbers written without a country code
+    pub phone_region: country::Id,
+    // Postal code CSV for the offline geocoder
+    pub geocoder_csv: String,
+    // Days deleted contacts and addresses stay in the trash
+    pub trash_retention_days: u32,
+    // Reject updates and deletes of contacts and addresses without If-Match
+    pub require_if_match: bool,
+    // Let webhooks reach loopback and private addresses, e.g. a local stub
+    pub webhook_allow_private_urls: bool,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let phone_region = std::env::var("PHONE_DEFAULT_REGION")
+            .unwrap_or_else(|_| "ID".to_string())
+            .to_uppercase()
+            .parse()
+            .expect("PHONE_DEFAULT_REGION must be an ISO 3166-1 alpha-2 region code");
+
+        let geocoder_csv = std::env::var("GEOCODER_POSTAL_CODES")
+            .unwrap_or_else(|_| "data/postal_codes.csv".to_string());
+
+        let trash_retention_days = std::env::var("TRASH_RETENTION_DAYS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .expect("TRASH_RETENTION_DAYS must be a number of days");
+
+        let require_if_match = std::env::var("REQUIRE_IF_MATCH")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
+
+        let webhook_allow_private_urls = std::env::var("WEBHOOK_ALLOW_PRIVATE_URLS")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
+
+        Self {
+            phone_region,
+            geocoder_csv,
+            trash_retention_days,
+            require_if_match,
+            webhook_allow_private_urls,
+        }
+    }
+}
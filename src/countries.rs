@@ -0,0 +1,376 @@
+use std::{collections::HashMap, sync::LazyLock};
+use regex::Regex;
+
+pub struct Country {
+    pub code: &'static str,
+    pub alpha3: &'static str,
+    pub name: &'static str,
+}
+
+const fn country(code: &'static str, alpha3: &'static str, name: &'static str) -> Country {
+    Country { code, alpha3, name }
+}
+
+// ISO 3166-1 countries with their English short names
+pub const COUNTRIES: [Country; 249] = [
+    country("AD", "AND", "Andorra"),
+    country("AE", "ARE", "United Arab Emirates"),
+    country("AF", "AFG", "Afghanistan"),
+    country("AG", "ATG", "Antigua and Barbuda"),
+    country("AI", "AIA", "Anguilla"),
+    country("AL", "ALB", "Albania"),
+    country("AM", "ARM", "Armenia"),
+    country("AO", "AGO", "Angola"),
+    country("AQ", "ATA", "Antarctica"),
+    country("AR", "ARG", "Argentina"),
+    country("AS", "ASM", "American Samoa"),
+    country("AT", "AUT", "Austria"),
+    country("AU", "AUS", "Australia"),
+    country("AW", "ABW", "Aruba"),
+    country("AX", "ALA", "Åland Islands"),
+    country("AZ", "AZE", "Azerbaijan"),
+    country("BA", "BIH", "Bosnia and Herzegovina"),
+    country("BB", "BRB", "Barbados"),
+    country("BD", "BGD", "Bangladesh"),
+    country("BE", "BEL", "Belgium"),
+    country("BF", "BFA", "Burkina Faso"),
+    country("BG", "BGR", "Bulgaria"),
+    country("BH", "BHR", "Bahrain"),
+    country("BI", "BDI", "Burundi"),
+    country("BJ", "BEN", "Benin"),
+    country("BL", "BLM", "Saint Barthélemy"),
+    country("BM", "BMU", "Bermuda"),
+    country("BN", "BRN", "Brunei Darussalam"),
+    country("BO", "BOL", "Bolivia"),
+    country("BQ", "BES", "Bonaire, Sint Eustatius and Saba"),
+    country("BR", "BRA", "Brazil"),
+    country("BS", "BHS", "Bahamas"),
+    country("BT", "BTN", "Bhutan"),
+    country("BV", "BVT", "Bouvet Island"),
+    country("BW", "BWA", "Botswana"),
+    country("BY", "BLR", "Belarus"),
+    country("BZ", "BLZ", "Belize"),
+    country("CA", "CAN", "Canada"),
+    country("CC", "CCK", "Cocos (Keeling) Islands"),
+    country("CD", "COD", "Congo, Democratic Republic of the"),
+    country("CF", "CAF", "Central African Republic"),
+    country("CG", "COG", "Congo"),
+    country("CH", "CHE", "Switzerland"),
+    country("CI", "CIV", "Côte d'Ivoire"),
+    country("CK", "COK", "Cook Islands"),
+    country("CL", "CHL", "Chile"),
+    country("CM", "CMR", "Cameroon"),
+    country("CN", "CHN", "China"),
+    country("CO", "COL", "Colombia"),
+    country("CR", "CRI", "Costa Rica"),
+    country("CU", "CUB", "Cuba"),
+    country("CV", "CPV", "Cabo Verde"),
+    country("CW", "CUW", "Curaçao"),
+    country("CX", "CXR", "Christmas Island"),
+    country("CY", "CYP", "Cyprus"),
+    country("CZ", "CZE", "Czechia"),
+    country("DE", "DEU", "Germany"),
+    country("DJ", "DJI", "Djibouti"),
+    country("DK", "DNK", "Denmark"),
+    country("DM", "DMA", "Dominica"),
+    country("DO", "DOM", "Dominican Republic"),
+    country("DZ", "DZA", "Algeria"),
+    country("EC", "ECU", "Ecuador"),
+    country("EE", "EST", "Estonia"),
+    country("EG", "EGY", "Egypt"),
+    country("EH", "ESH", "Western Sahara"),
+    country("ER", "ERI", "Eritrea"),
+    country("ES", "ESP", "Spain"),
+    country("ET", "ETH", "Ethiopia"),
+    country("FI", "FIN", "Finland"),
+    country("FJ", "FJI", "Fiji"),
+    country("FK", "FLK", "Falkland Islands (Malvinas)"),
+    country("FM", "FSM", "Micronesia"),
+    country("FO", "FRO", "Faroe Islands"),
+    country("FR", "FRA", "France"),
+    country("GA", "GAB", "Gabon"),
+    country("GB", "GBR", "United Kingdom"),
+    country("GD", "GRD", "Grenada"),
+    country("GE", "GEO", "Georgia"),
+    country("GF", "GUF", "French Guiana"),
+    country("GG", "GGY", "Guernsey"),
+    country("GH", "GHA", "Ghana"),
+    country("GI", "GIB", "Gibraltar"),
+    country("GL", "GRL", "Greenland"),
+    country("GM", "GMB", "Gambia"),
+    country("GN", "GIN", "Guinea"),
+    country("GP", "GLP", "Guadeloupe"),
+    country("GQ", "GNQ", "Equatorial Guinea"),
+    country("GR", "GRC", "Greece"),
+    country("GS", "SGS", "South Georgia and the South Sandwich Islands"),
+    country("GT", "GTM", "Guatemala"),
+    country("GU", "GUM", "Guam"),
+    country("GW", "GNB", "Guinea-Bissau"),
+    country("GY", "GUY", "Guyana"),
+    country("HK", "HKG", "Hong Kong"),
+    country("HM", "HMD", "Heard Island and McDonald Islands"),
+    country("HN", "HND", "Honduras"),
+    country("HR", "HRV", "Croatia"),
+    country("HT", "HTI", "Haiti"),
+    country("HU", "HUN", "Hungary"),
+    country("ID", "IDN", "Indonesia"),
+    country("IE", "IRL", "Ireland"),
+    country("IL", "ISR", "Israel"),
+    country("IM", "IMN", "Isle of Man"),
+    country("IN", "IND", "India"),
+    country("IO", "IOT", "British Indian Ocean Territory"),
+    country("IQ", "IRQ", "Iraq"),
+    country("IR", "IRN", "Iran"),
+    country("IS", "ISL", "Iceland"),
+    country("IT", "ITA", "Italy"),
+    country("JE", "JEY", "Jersey"),
+    country("JM", "JAM", "Jamaica"),
+    country("JO", "JOR", "Jordan"),
+    country("JP", "JPN", "Japan"),
+    country("KE", "KEN", "Kenya"),
+    country("KG", "KGZ", "Kyrgyzstan"),
+    country("KH", "KHM", "Cambodia"),
+    country("KI", "KIR", "Kiribati"),
+    country("KM", "COM", "Comoros"),
+    country("KN", "KNA", "Saint Kitts and Nevis"),
+    country("KP", "PRK", "North Korea"),
+    country("KR", "KOR", "South Korea"),
+    country("KW", "KWT", "Kuwait"),
+    country("KY", "CYM", "Cayman Islands"),
+    country("KZ", "KAZ", "Kazakhstan"),
+    country("LA", "LAO", "Laos"),
+    country("LB", "LBN", "Lebanon"),
+    country("LC", "LCA", "Saint Lucia"),
+    country("LI", "LIE", "Liechtenstein"),
+    country("LK", "LKA", "Sri Lanka"),
+    country("LR", "LBR", "Liberia"),
+    country("LS", "LSO", "Lesotho"),
+    country("LT", "LTU", "Lithuania"),
+    country("LU", "LUX", "Luxembourg"),
+    country("LV", "LVA", "Latvia"),
+    country("LY", "LBY", "Libya"),
+    country("MA", "MAR", "Morocco"),
+    country("MC", "MCO", "Monaco"),
+    country("MD", "MDA", "Moldova"),
+    country("ME", "MNE", "Montenegro"),
+    country("MF", "MAF", "Saint Martin (French part)"),
+    country("MG", "MDG", "Madagascar"),
+    country("MH", "MHL", "Marshall Islands"),
+    country("MK", "MKD", "North Macedonia"),
+    country("ML", "MLI", "Mali"),
+    country("MM", "MMR", "Myanmar"),
+    country("MN", "MNG", "Mongolia"),
+    country("MO", "MAC", "Macao"),
+    country("MP", "MNP", "Northern Mariana Islands"),
+    country("MQ", "MTQ", "Martinique"),
+    country("MR", "MRT", "Mauritania"),
+    country("MS", "MSR", "Montserrat"),
+    country("MT", "MLT", "Malta"),
+    country("MU", "MUS", "Mauritius"),
+    country("MV", "MDV", "Maldives"),
+    country("MW", "MWI", "Malawi"),
+    country("MX", "MEX", "Mexico"),
+    country("MY", "MYS", "Malaysia"),
+    country("MZ", "MOZ", "Mozambique"),
+    country("NA", "NAM", "Namibia"),
+    country("NC", "NCL", "New Caledonia"),
+    country("NE", "NER", "Niger"),
+    country("NF", "NFK", "Norfolk Island"),
+    country("NG", "NGA", "Nigeria"),
+    country("NI", "NIC", "Nicaragua"),
+    country("NL", "NLD", "Netherlands"),
+    country("NO", "NOR", "Norway"),
+    country("NP", "NPL", "Nepal"),
+    country("NR", "NRU", "Nauru"),
+    country("NU", "NIU", "Niue"),
+    country("NZ", "NZL", "New Zealand"),
+    country("OM", "OMN", "Oman"),
+    country("PA", "PAN", "Panama"),
+    country("PE", "PER", "Peru"),
+    country("PF", "PYF", "French Polynesia"),
+    country("PG", "PNG", "Papua New Guinea"),
+    country("PH", "PHL", "Philippines"),
+    country("PK", "PAK", "Pakistan"),
+    country("PL", "POL", "Poland"),
+    country("PM", "SPM", "Saint Pierre and Miquelon"),
+    country("PN", "PCN", "Pitcairn"),
+    country("PR", "PRI", "Puerto Rico"),
+    country("PS", "PSE", "Palestine"),
+    country("PT", "PRT", "Portugal"),
+    country("PW", "PLW", "Palau"),
+    country("PY", "PRY", "Paraguay"),
+    country("QA", "QAT", "Qatar"),
+    country("RE", "REU", "Réunion"),
+    country("RO", "ROU", "Romania"),
+    country("RS", "SRB", "Serbia"),
+    country("RU", "RUS", "Russia"),
+    country("RW", "RWA", "Rwanda"),
+    country("SA", "SAU", "Saudi Arabia"),
+    country("SB", "SLB", "Solomon Islands"),
+    country("SC", "SYC", "Seychelles"),
+    country("SD", "SDN", "Sudan"),
+    country("SE", "SWE", "Sweden"),
+    country("SG", "SGP", "Singapore"),
+    country("SH", "SHN", "Saint Helena, Ascension and Tristan da Cunha"),
+    country("SI", "SVN", "Slovenia"),
+    country("SJ", "SJM", "Svalbard and Jan Mayen"),
+    country("SK", "SVK", "Slovakia"),
+    country("SL", "SLE", "Sierra Leone"),
+    country("SM", "SMR", "San Marino"),
+    country("SN", "SEN", "Senegal"),
+    country("SO", "SOM", "Somalia"),
+    country("SR", "SUR", "Suriname"),
+    country("SS", "SSD", "South Sudan"),
+    country("ST", "STP", "Sao Tome and Principe"),
+    country("SV", "SLV", "El Salvador"),
+    country("SX", "SXM", "Sint Maarten (Dutch part)"),
+    country("SY", "SYR", "Syria"),
+    country("SZ", "SWZ", "Eswatini"),
+    country("TC", "TCA", "Turks and Caicos Islands"),
+    country("TD", "TCD", "Chad"),
+    country("TF", "ATF", "French Southern Territories"),
+    country("TG", "TGO", "Togo"),
+    country("TH", "THA", "Thailand"),
+    country("TJ", "TJK", "Tajikistan"),
+    country("TK", "TKL", "Tokelau"),
+    country("TL", "TLS", "Timor-Leste"),
+    country("TM", "TKM", "Turkmenistan"),
+    country("TN", "TUN", "Tunisia"),
+    country("TO", "TON", "Tonga"),
+    country("TR", "TUR", "Türkiye"),
+    country("TT", "TTO", "Trinidad and Tobago"),
+    country("TV", "TUV", "Tuvalu"),
+    country("TW", "TWN", "Taiwan"),
+    country("TZ", "TZA", "Tanzania"),
+    country("UA", "UKR", "Ukraine"),
+    country("UG", "UGA", "Uganda"),
+    country("UM", "UMI", "United States Minor Outlying Islands"),
+    country("US", "USA", "United States"),
+    country("UY", "URY", "Uruguay"),
+    country("UZ", "UZB", "Uzbekistan"),
+    country("VA", "VAT", "Holy See"),
+    country("VC", "VCT", "Saint Vincent and the Grenadines"),
+    country("VE", "VEN", "Venezuela"),
+    country("VG", "VGB", "Virgin Islands (British)"),
+    country("VI", "VIR", "Virgin Islands (U.S.)"),
+    country("VN", "VNM", "Viet Nam"),
+    country("VU", "VUT", "Vanuatu"),
+    country("WF", "WLF", "Wallis and Futuna"),
+    country("WS", "WSM", "Samoa"),
+    country("YE", "YEM", "Yemen"),
+    country("YT", "MYT", "Mayotte"),
+    country("ZA", "ZAF", "South Africa"),
+    country("ZM", "ZMB", "Zambia"),
+    country("ZW", "ZWE", "Zimbabwe"),
+];
+
+// Other names people commonly write
+const ALIASES: [(&str, &str); 14] = [
+    ("usa", "US"),
+    ("united states of america", "US"),
+    ("america", "US"),
+    ("uk", "GB"),
+    ("great britain", "GB"),
+    ("england", "GB"),
+    ("korea", "KR"),
+    ("republic of korea", "KR"),
+    ("vietnam", "VN"),
+    ("turkey", "TR"),
+    ("czech republic", "CZ"),
+    ("brunei", "BN"),
+    ("holland", "NL"),
+    ("timor leste", "TL"),
+];
+
+// Postal code formats for countries where they are well known. Codes are
+// compared in upper case.
+const POSTAL_CODES: [(&str, &str); 36] = [
+    ("AT", r"^\d{4}$"),
+    ("AU", r"^\d{4}$"),
+    ("BE", r"^\d{4}$"),
+    ("BR", r"^\d{5}-?\d{3}$"),
+    ("CA", r"^[A-Z]\d[A-Z] ?\d[A-Z]\d$"),
+    ("CH", r"^\d{4}$"),
+    ("CN", r"^\d{6}$"),
+    ("DE", r"^\d{5}$"),
+    ("DK", r"^\d{4}$"),
+    ("ES", r"^\d{5}$"),
+    ("FI", r"^\d{5}$"),
+    ("FR", r"^\d{5}$"),
+    ("GB", r"^[A-Z]{1,2}\d[A-Z\d]? ?\d[A-Z]{2}$"),
+    ("ID", r"^\d{5}$"),
+    ("IE", r"^[A-Z]\d[\dW] ?[A-Z\d]{4}$"),
+    ("IN", r"^\d{3} ?\d{3}$"),
+    ("IT", r"^\d{5}$"),
+    ("JP", r"^\d{3}-?\d{4}$"),
+    ("KR", r"^\d{5}$"),
+    ("MX", r"^\d{5}$"),
+    ("MY", r"^\d{5}$"),
+    ("NL", r"^\d{4} ?[A-Z]{2}$"),
+    ("NO", r"^\d{4}$"),
+    ("NZ", r"^\d{4}$"),
+    ("PH", r"^\d{4}$"),
+    ("PL", r"^\d{2}-\d{3}$"),
+    ("PT", r"^\d{4}-\d{3}$"),
+    ("RU", r"^\d{6}$"),
+    ("SA", r"^\d{5}(-\d{4})?$"),
+    ("SE", r"^\d{3} ?\d{2}$"),
+    ("SG", r"^\d{6}$"),
+    ("TH", r"^\d{5}$"),
+    ("TW", r"^\d{3}(\d{2,3})?$"),
+    ("US", r"^\d{5}(-\d{4})?$"),
+    ("VN", r"^\d{6}$"),
+    ("ZA", r"^\d{4}$"),
+];
+
+static POSTAL_CODE_PATTERNS: LazyLock<HashMap<&'static str, Regex>> = LazyLock::new(|| {
+    POSTAL_CODES
+        .iter()
+        .map(|(code, pattern)| (*code, Regex::new(pattern).expect("valid postal code pattern")))
+        .collect()
+});
+
+// Looks a country up by alpha-2 code, alpha-3 code, English name or a
+// common alias, ignoring case.
+pub fn find(value: &str) -> Option<&'static Country> {
+    let value = value.trim();
+    let lower = value.to_lowercase();
+
+    let code = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == lower)
+        .map(|(_, code)| *code);
+
+    COUNTRIES.iter().find(|country| {
+        Some(country.code) == code
+            || country.code.eq_ignore_ascii_case(value)
+            || country.alpha3.eq_ignore_ascii_case(value)
+            || country.name.to_lowercase() == lower
+    })
+}
+
+pub fn name(code: &str) -> Option<&'static str> {
+    COUNTRIES
+        .iter()
+        .find(|country| country.code == code)
+        .map(|country| country.name)
+}
+
+// Closest country name, for suggesting a fix for typos like "Indonsia"
+pub fn suggest(value: &str) -> Option<&'static Country> {
+    let lower = value.trim().to_lowercase();
+
+    COUNTRIES
+        .iter()
+        .map(|country| (country, strsim::jaro_winkler(&country.name.to_lowercase(), &lower)))
+        .filter(|(_, similarity)| *similarity >= 0.85)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(country, _)| country)
+}
+
+// Countries without a known format accept any postal code
+pub fn is_valid_postal_code(code: &str, postal_code: &str) -> bool {
+    POSTAL_CODE_PATTERNS
+        .get(code)
+        .is_none_or(|pattern| pattern.is_match(&postal_code.trim().to_uppercase()))
+}
@@ -1,6 +1,10 @@
+use std::sync::Arc;
 use sqlx::MySqlPool;
+use crate::{config::Config, geocoder::Geocoder};
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: MySqlPool,
+    pub config: Config,
+    pub geocoder: Arc<dyn Geocoder>,
 }
\ No newline at end of file
@@ -19,16 +19,26 @@ pub enum AppError {
     #[error("Unauthorized")]
     Unauthorized,
     
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    
     #[error("Bad request: {0}")]
     BadRequest(String),
+
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+
+    #[error("Precondition required: {0}")]
+    PreconditionRequired(String),
     
     #[error("Internal server error")]
     Internal,
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, message) = match self {
+impl AppError {
+    // Status and message sent to the client
+    pub fn into_parts(self) -> (StatusCode, String) {
+        match self {
             AppError::Database(err) => {
                 tracing::error!("Database error: {:?}", err);
                 (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
@@ -36,11 +46,20 @@ impl IntoResponse for AppError {
             AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::PreconditionFailed(msg) => (StatusCode::PRECONDITION_FAILED, msg),
+            AppError::PreconditionRequired(msg) => (StatusCode::PRECONDITION_REQUIRED, msg),
             AppError::Internal => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
             }
-        };
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, message) = self.into_parts();
 
         let body = Json(ErrorResponse { errors: message });
         (status, body).into_response()
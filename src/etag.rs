@@ -0,0 +1,135 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use crate::{
+    database::AppState,
+    errors::{AppError, AppResult},
+};
+
+// Contacts and addresses are tagged with their row version
+pub fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+// Tag of another representation of the same version, e.g. an address in
+// another format
+pub fn variant_etag(version: i32, variant: &str) -> String {
+    format!("\"{}-{}\"", version, variant)
+}
+
+// Any representation of the version, since updates and deletes apply to
+// all of them
+fn is_version(tag: &str, version: i32) -> bool {
+    tag.strip_prefix('"')
+        .and_then(|tag| tag.strip_suffix('"'))
+        .and_then(|tag| tag.split('-').next())
+        .is_some_and(|tag| tag == version.to_string())
+}
+
+// Adds the ETag header to a response
+pub fn tagged<T: IntoResponse>(version: i32, body: T) -> Response {
+    with_etag(etag(version), body)
+}
+
+pub fn with_etag<T: IntoResponse>(tag: String, body: T) -> Response {
+    match HeaderValue::from_str(&tag) {
+        Ok(value) => ([(header::ETAG, value)], body).into_response(),
+        Err(_) => body.into_response(),
+    }
+}
+
+fn tags(headers: &HeaderMap, name: header::HeaderName) -> Option<Vec<String>> {
+    let values: Vec<String> = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+
+    (!values.is_empty()).then_some(values)
+}
+
+// `If-Match` of an update or delete. Without the header any version is
+// accepted, unless `REQUIRE_IF_MATCH` is set.
+pub enum IfMatch {
+    Any,
+    Tags(Vec<String>),
+}
+
+impl IfMatch {
+    // For requests that send the expected version in the body
+    pub fn from_version(version: Option<i32>, required: bool) -> AppResult<Self> {
+        match version {
+            Some(version) => Ok(IfMatch::Tags(vec![etag(version)])),
+            None if required => Err(AppError::PreconditionRequired("version is required".to_string())),
+            None => Ok(IfMatch::Any),
+        }
+    }
+
+    // Strong comparison, so weak tags never match
+    pub fn check(&self, version: i32) -> AppResult<()> {
+        match self {
+            IfMatch::Any => Ok(()),
+            IfMatch::Tags(tags) if tags.iter().any(|tag| tag == "*" || is_version(tag, version)) => Ok(()),
+            IfMatch::Tags(_) => Err(AppError::PreconditionFailed(format!(
+                "resource has changed, current ETag is {}",
+                etag(version)
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for IfMatch {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        match tags(&parts.headers, header::IF_MATCH) {
+            Some(tags) => Ok(IfMatch::Tags(tags)),
+            None if state.config.require_if_match => {
+                Err(AppError::PreconditionRequired("If-Match header is required".to_string()))
+            }
+            None => Ok(IfMatch::Any),
+        }
+    }
+}
+
+// `If-None-Match` of a read
+pub struct IfNoneMatch(Option<Vec<String>>);
+
+impl IfNoneMatch {
+    // Weak comparison, as reads allow
+    pub fn matches(&self, current: &str) -> bool {
+        self.0.as_ref().is_some_and(|tags| {
+            tags.iter().any(|tag| tag == "*" || tag.trim_start_matches("W/") == current)
+        })
+    }
+
+    // 304 when the caller's copy is current, otherwise the tagged body
+    pub fn respond<T: IntoResponse>(&self, version: i32, body: T) -> Response {
+        self.respond_with(etag(version), body)
+    }
+
+    pub fn respond_with<T: IntoResponse>(&self, tag: String, body: T) -> Response {
+        if self.matches(&tag) {
+            with_etag(tag, StatusCode::NOT_MODIFIED)
+        } else {
+            with_etag(tag, body)
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfNoneMatch {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(IfNoneMatch(tags(&parts.headers, header::IF_NONE_MATCH)))
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use async_trait::async_trait;
+use crate::errors::AppResult;
+
+// What a geocoder gets to locate an address
+pub struct GeocodeQuery<'a> {
+    pub country: &'a str,
+    pub postal_code: &'a str,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+// Looks up coordinates for an address. Returns None when the address
+// can't be located; errors are for failures worth retrying.
+#[async_trait]
+pub trait Geocoder: Send + Sync {
+    async fn geocode(&self, query: &GeocodeQuery<'_>) -> AppResult<Option<Coordinates>>;
+}
+
+// Offline geocoder that places an address at the center of its postal code,
+// read from a `country,postal_code,latitude,longitude` CSV. Meant for
+// development and tests.
+pub struct PostalCodeGeocoder {
+    locations: HashMap<(String, String), Coordinates>,
+}
+
+impl PostalCodeGeocoder {
+    pub fn from_csv(content: &str) -> Result<Self, String> {
+        let mut locations = HashMap::new();
+
+        for (index, line) in content.lines().enumerate().skip(1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [country, postal_code, latitude, longitude] = fields[..] else {
+                return Err(format!("line {} must have 4 fields", index + 1));
+            };
+            let coordinates = Coordinates {
+                latitude: latitude.parse().map_err(|_| format!("line {} has an invalid latitude", index + 1))?,
+                longitude: longitude.parse().map_err(|_| format!("line {} has an invalid longitude", index + 1))?,
+            };
+            locations.insert(key(country, postal_code), coordinates);
+        }
+
+        Ok(Self { locations })
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_csv(&content)
+    }
+
+    pub fn empty() -> Self {
+        Self { locations: HashMap::new() }
+    }
+}
+
+fn key(country: &str, postal_code: &str) -> (String, String) {
+    (country.to_uppercase(), postal_code.replace(' ', "").to_uppercase())
+}
+
+#[async_trait]
+impl Geocoder for PostalCodeGeocoder {
+    async fn geocode(&self, query: &GeocodeQuery<'_>) -> AppResult<Option<Coordinates>> {
+        Ok(self.locations.get(&key(query.country, query.postal_code)).copied())
+    }
+}
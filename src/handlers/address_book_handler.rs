@@ -0,0 +1,87 @@
+use axum::{
+    extract::{Extension, Path, State},
+    Json,
+};
+use std::sync::Arc;
+use crate::{
+    database::AppState,
+    errors::AppResult,
+    models::*,
+    services::address_book_service,
+    validation::validate_request,
+};
+
+pub async fn create(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<CreateAddressBookRequest>,
+) -> AppResult<Json<ApiResponse<AddressBookResponse>>> {
+    validate_request(&req)?;
+    let book = address_book_service::create(&state.pool, &user.username, req).await?;
+    Ok(Json(ApiResponse { data: book }))
+}
+
+pub async fn list(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> AppResult<Json<ApiResponse<Vec<AddressBookResponse>>>> {
+    let books = address_book_service::list(&state.pool, &user.username).await?;
+    Ok(Json(ApiResponse { data: books }))
+}
+
+pub async fn get(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<AddressBookResponse>>> {
+    let book = address_book_service::get(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: book }))
+}
+
+pub async fn update(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateAddressBookRequest>,
+) -> AppResult<Json<ApiResponse<AddressBookResponse>>> {
+    validate_request(&req)?;
+    let book = address_book_service::update(&state.pool, &user.username, id, req).await?;
+    Ok(Json(ApiResponse { data: book }))
+}
+
+pub async fn remove(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<String>>> {
+    address_book_service::remove(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: "OK".to_string() }))
+}
+
+pub async fn list_members(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<Vec<AddressBookMemberResponse>>>> {
+    let members = address_book_service::list_members(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: members }))
+}
+
+pub async fn set_member(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path((id, member)): Path<(i32, String)>,
+    Json(req): Json<UpdateMemberRequest>,
+) -> AppResult<Json<ApiResponse<Vec<AddressBookMemberResponse>>>> {
+    let members = address_book_service::set_member(&state.pool, &user.username, id, &member, req).await?;
+    Ok(Json(ApiResponse { data: members }))
+}
+
+pub async fn remove_member(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path((id, member)): Path<(i32, String)>,
+) -> AppResult<Json<ApiResponse<String>>> {
+    address_book_service::remove_member(&state.pool, &user.username, id, &member).await?;
+    Ok(Json(ApiResponse { data: "OK".to_string() }))
+}
@@ -1,11 +1,13 @@
 use axum::{
-    extract::{Extension, Path, State},
+    extract::{Extension, Path, Query, State},
+    response::Response,
     Json,
 };
 use std::sync::Arc;
 use crate::{
     database::AppState,
-    errors::{AppError, AppResult},
+    errors::AppResult,
+    etag::{self, IfMatch, IfNoneMatch},
     models::*,
     services::address_service,
     validation::validate_request,
@@ -18,7 +20,7 @@ pub async fn create(
     Json(req): Json<CreateAddressRequest>,
 ) -> AppResult<Json<ApiResponse<AddressResponse>>> {
     validate_request(&req)?;
-    let address = address_service::create(&state.pool, &user.username, contact_id, req).await?;
+    let address = address_service::create(&state.pool, &state.geocoder, &user.username, contact_id, req).await?;
     Ok(Json(ApiResponse { data: address }))
 }
 
@@ -26,31 +28,81 @@ pub async fn get(
     State(state): State<Arc<AppState>>,
     Extension(user): Extension<User>,
     Path((contact_id, address_id)): Path<(i32, i32)>,
-) -> AppResult<Json<ApiResponse<AddressResponse>>> {
-    let address = address_service::get(&state.pool, &user.username, contact_id, address_id).await?;
-    Ok(Json(ApiResponse { data: address }))
+    Query(req): Query<GetAddressRequest>,
+    if_none_match: IfNoneMatch,
+) -> AppResult<Response> {
+    let format = req.format.unwrap_or_default();
+    let address = address_service::get(&state.pool, &user.username, contact_id, address_id, format).await?;
+
+    // Each format is its own representation, so it gets its own tag
+    let tag = match format {
+        AddressFormat::Label => etag::etag(address.version),
+        _ => etag::variant_etag(address.version, format.as_str()),
+    };
+    Ok(if_none_match.respond_with(tag, Json(ApiResponse { data: address })))
 }
 
 pub async fn update(
     State(state): State<Arc<AppState>>,
     Extension(user): Extension<User>,
     Path((contact_id, address_id)): Path<(i32, i32)>,
+    if_match: IfMatch,
     Json(req): Json<UpdateAddressRequest>,
-) -> AppResult<Json<ApiResponse<AddressResponse>>> {
+) -> AppResult<Response> {
     validate_request(&req)?;
-    let address = address_service::update(&state.pool, &user.username, contact_id, address_id, req).await?;
-    Ok(Json(ApiResponse { data: address }))
+    let address = address_service::update(
+        &state.pool,
+        &state.geocoder,
+        &user.username,
+        contact_id,
+        address_id,
+        req,
+        &if_match,
+    )
+    .await?;
+    Ok(etag::tagged(address.version, Json(ApiResponse { data: address })))
+}
+
+// Body is an RFC 7396 merge patch
+pub async fn patch(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path((contact_id, address_id)): Path<(i32, i32)>,
+    if_match: IfMatch,
+    Json(patch): Json<serde_json::Value>,
+) -> AppResult<Response> {
+    let address = address_service::patch(
+        &state.pool,
+        &state.geocoder,
+        &user.username,
+        contact_id,
+        address_id,
+        patch,
+        &if_match,
+    )
+    .await?;
+    Ok(etag::tagged(address.version, Json(ApiResponse { data: address })))
 }
 
 pub async fn remove(
     State(state): State<Arc<AppState>>,
     Extension(user): Extension<User>,
     Path((contact_id, address_id)): Path<(i32, i32)>,
+    if_match: IfMatch,
 ) -> AppResult<Json<ApiResponse<String>>> {
-    address_service::remove(&state.pool, &user.username, contact_id, address_id).await?;
+    address_service::remove(&state.pool, &user.username, contact_id, address_id, &if_match).await?;
     Ok(Json(ApiResponse { data: "OK".to_string() }))
 }
 
+pub async fn restore(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path((contact_id, address_id)): Path<(i32, i32)>,
+) -> AppResult<Json<ApiResponse<AddressResponse>>> {
+    let address = address_service::restore(&state.pool, &user.username, contact_id, address_id).await?;
+    Ok(Json(ApiResponse { data: address }))
+}
+
 pub async fn list(
     State(state): State<Arc<AppState>>,
     Extension(user): Extension<User>,
@@ -58,4 +110,23 @@ pub async fn list(
 ) -> AppResult<Json<ApiResponse<Vec<AddressResponse>>>> {
     let addresses = address_service::list(&state.pool, &user.username, contact_id).await?;
     Ok(Json(ApiResponse { data: addresses }))
-}
\ No newline at end of file
+}
+
+pub async fn search(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Query(req): Query<SearchAddressRequest>,
+) -> AppResult<Json<AddressSearchResponse>> {
+    let result = address_service::search(&state.pool, &user.username, req).await?;
+    Ok(Json(result))
+}
+
+pub async fn nearby(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Query(req): Query<NearbyAddressRequest>,
+) -> AppResult<Json<ApiResponse<Vec<NearbyAddressResponse>>>> {
+    validate_request(&req)?;
+    let addresses = address_service::nearby(&state.pool, &user.username, req).await?;
+    Ok(Json(ApiResponse { data: addresses }))
+}
@@ -0,0 +1,20 @@
+use axum::{
+    extract::{Extension, Query, State},
+    Json,
+};
+use std::sync::Arc;
+use crate::{
+    database::AppState,
+    errors::AppResult,
+    models::*,
+    services::audit_service,
+};
+
+pub async fn search(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Query(req): Query<SearchAuditRequest>,
+) -> AppResult<Json<AuditLogResponse>> {
+    let result = audit_service::search(&state.pool, &user, req).await?;
+    Ok(Json(result))
+}
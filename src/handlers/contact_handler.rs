@@ -1,14 +1,17 @@
 use axum::{
     extract::{Extension, Path, Query, State},
+    response::Response,
     Json,
 };
 use std::sync::Arc;
 use crate::{
     database::AppState,
-    errors::{AppError, AppResult},
+    errors::AppResult,
+    etag::{self, IfMatch, IfNoneMatch},
     models::*,
-    services::contact_service,
+    services::{audit_service, batch_service, contact_service, duplicate_service, import_service, revision_service},
     validation::validate_request,
+    vcard,
 };
 
 pub async fn create(
@@ -17,44 +20,168 @@ pub async fn create(
     Json(req): Json<CreateContactRequest>,
 ) -> AppResult<Json<ApiResponse<ContactResponse>>> {
     validate_request(&req)?;
-    let contact = contact_service::create(&state.pool, &user.username, req).await?;
+    let contact = contact_service::create(&state.pool, state.config.phone_region, &user.username, req).await?;
     Ok(Json(ApiResponse { data: contact }))
 }
 
+pub async fn batch(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<BatchContactRequest>,
+) -> AppResult<Json<ApiResponse<BatchContactResponse>>> {
+    let result = batch_service::run(
+        &state.pool,
+        state.config.phone_region,
+        state.config.require_if_match,
+        &user.username,
+        req,
+    )
+    .await?;
+    Ok(Json(ApiResponse { data: result }))
+}
+
 pub async fn get(
     State(state): State<Arc<AppState>>,
     Extension(user): Extension<User>,
     Path(id): Path<i32>,
-) -> AppResult<Json<ApiResponse<ContactResponse>>> {
+    if_none_match: IfNoneMatch,
+) -> AppResult<Response> {
     let contact = contact_service::get(&state.pool, &user.username, id).await?;
-    Ok(Json(ApiResponse { data: contact }))
+    Ok(if_none_match.respond(contact.version, Json(ApiResponse { data: contact })))
 }
 
 pub async fn update(
     State(state): State<Arc<AppState>>,
     Extension(user): Extension<User>,
     Path(id): Path<i32>,
+    if_match: IfMatch,
     Json(req): Json<UpdateContactRequest>,
-) -> AppResult<Json<ApiResponse<ContactResponse>>> {
+) -> AppResult<Response> {
     validate_request(&req)?;
-    let contact = contact_service::update(&state.pool, &user.username, id, req).await?;
-    Ok(Json(ApiResponse { data: contact }))
+    let contact =
+        contact_service::update(&state.pool, state.config.phone_region, &user.username, id, req, &if_match).await?;
+    Ok(etag::tagged(contact.version, Json(ApiResponse { data: contact })))
+}
+
+// Body is an RFC 7396 merge patch
+pub async fn patch(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    if_match: IfMatch,
+    Json(patch): Json<serde_json::Value>,
+) -> AppResult<Response> {
+    let contact =
+        contact_service::patch(&state.pool, state.config.phone_region, &user.username, id, patch, &if_match).await?;
+    Ok(etag::tagged(contact.version, Json(ApiResponse { data: contact })))
 }
 
 pub async fn remove(
     State(state): State<Arc<AppState>>,
     Extension(user): Extension<User>,
     Path(id): Path<i32>,
+    if_match: IfMatch,
 ) -> AppResult<Json<ApiResponse<String>>> {
-    contact_service::remove(&state.pool, &user.username, id).await?;
+    contact_service::remove(&state.pool, &user.username, id, &if_match).await?;
     Ok(Json(ApiResponse { data: "OK".to_string() }))
 }
 
+pub async fn history(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    Query(req): Query<ContactHistoryRequest>,
+) -> AppResult<Json<AuditLogResponse>> {
+    let result = audit_service::contact_history(&state.pool, &user.username, id, req).await?;
+    Ok(Json(result))
+}
+
+pub async fn revisions(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<Vec<ContactRevisionResponse>>>> {
+    let revisions = revision_service::list(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: revisions }))
+}
+
+pub async fn revision(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path((id, rev)): Path<(i32, i32)>,
+) -> AppResult<Json<ApiResponse<ContactRevisionResponse>>> {
+    let revision = revision_service::get(&state.pool, &user.username, id, rev).await?;
+    Ok(Json(ApiResponse { data: revision }))
+}
+
+pub async fn revert(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path((id, rev)): Path<(i32, i32)>,
+) -> AppResult<Json<ApiResponse<ContactResponse>>> {
+    let contact = revision_service::revert(
+        &state.pool,
+        &state.geocoder,
+        state.config.phone_region,
+        &user.username,
+        id,
+        rev,
+    )
+    .await?;
+    Ok(Json(ApiResponse { data: contact }))
+}
+
+pub async fn restore(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<ContactResponse>>> {
+    let contact = contact_service::restore(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: contact }))
+}
+
 pub async fn search(
     State(state): State<Arc<AppState>>,
     Extension(user): Extension<User>,
     Query(req): Query<SearchContactRequest>,
 ) -> AppResult<Json<ContactSearchResponse>> {
-    let result = contact_service::search(&state.pool, &user.username, req).await?;
+    let result = contact_service::search(&state.pool, state.config.phone_region, &user.username, req).await?;
     Ok(Json(result))
-}
\ No newline at end of file
+}
+
+pub async fn import_vcard(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Query(params): Query<ImportContactsRequest>,
+    body: String,
+) -> AppResult<Json<ApiResponse<ContactImportResponse>>> {
+    let cards = vcard::parse(&body)?;
+    let result = import_service::import_vcard(
+        &state.pool,
+        &state.geocoder,
+        state.config.phone_region,
+        &user.username,
+        params.address_book_id,
+        cards,
+    )
+    .await?;
+    Ok(Json(ApiResponse { data: result }))
+}
+
+pub async fn duplicates(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> AppResult<Json<ApiResponse<Vec<DuplicateGroupResponse>>>> {
+    let groups = duplicate_service::find_duplicates(&state.pool, &user.username).await?;
+    Ok(Json(ApiResponse { data: groups }))
+}
+
+pub async fn merge(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    Json(req): Json<MergeContactRequest>,
+) -> AppResult<Json<ApiResponse<ContactResponse>>> {
+    let contact = duplicate_service::merge(&state.pool, &user.username, id, req).await?;
+    Ok(Json(ApiResponse { data: contact }))
+}
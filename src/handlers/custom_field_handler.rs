@@ -0,0 +1,50 @@
+use axum::{
+    extract::{Extension, Path, State},
+    Json,
+};
+use std::sync::Arc;
+use crate::{
+    database::AppState,
+    errors::AppResult,
+    models::*,
+    services::custom_field_service,
+    validation::validate_request,
+};
+
+pub async fn create(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<CreateCustomFieldRequest>,
+) -> AppResult<Json<ApiResponse<CustomFieldResponse>>> {
+    validate_request(&req)?;
+    let field = custom_field_service::create(&state.pool, &user.username, req).await?;
+    Ok(Json(ApiResponse { data: field }))
+}
+
+pub async fn list(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> AppResult<Json<ApiResponse<Vec<CustomFieldResponse>>>> {
+    let fields = custom_field_service::list(&state.pool, &user.username).await?;
+    Ok(Json(ApiResponse { data: fields }))
+}
+
+pub async fn update(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateCustomFieldRequest>,
+) -> AppResult<Json<ApiResponse<CustomFieldResponse>>> {
+    validate_request(&req)?;
+    let field = custom_field_service::update(&state.pool, &user.username, id, req).await?;
+    Ok(Json(ApiResponse { data: field }))
+}
+
+pub async fn remove(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<String>>> {
+    custom_field_service::remove(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: "OK".to_string() }))
+}
@@ -1,4 +1,11 @@
 pub mod user_handler;
 pub mod contact_handler;
 pub mod address_handler;
-pub mod health_handler;
\ No newline at end of file
+pub mod address_book_handler;
+pub mod health_handler;
+pub mod tag_handler;
+pub mod custom_field_handler;
+pub mod saved_search_handler;
+pub mod trash_handler;
+pub mod audit_handler;
+pub mod webhook_handler;
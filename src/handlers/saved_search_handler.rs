@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    Json,
+};
+use std::sync::Arc;
+use crate::{
+    database::AppState,
+    errors::AppResult,
+    models::*,
+    services::saved_search_service,
+    validation::validate_request,
+};
+
+pub async fn create(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<SavedSearchRequest>,
+) -> AppResult<Json<ApiResponse<SavedSearchResponse>>> {
+    validate_request(&req)?;
+    let search = saved_search_service::create(&state.pool, &user.username, req).await?;
+    Ok(Json(ApiResponse { data: search }))
+}
+
+pub async fn list(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> AppResult<Json<ApiResponse<Vec<SavedSearchResponse>>>> {
+    let searches = saved_search_service::list(&state.pool, &user.username).await?;
+    Ok(Json(ApiResponse { data: searches }))
+}
+
+pub async fn get(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<SavedSearchResponse>>> {
+    let search = saved_search_service::get(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: search }))
+}
+
+pub async fn update(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    Json(req): Json<SavedSearchRequest>,
+) -> AppResult<Json<ApiResponse<SavedSearchResponse>>> {
+    validate_request(&req)?;
+    let search = saved_search_service::update(&state.pool, &user.username, id, req).await?;
+    Ok(Json(ApiResponse { data: search }))
+}
+
+pub async fn remove(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<String>>> {
+    saved_search_service::remove(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: "OK".to_string() }))
+}
+
+pub async fn contacts(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    Query(paging): Query<SavedSearchContactsRequest>,
+) -> AppResult<Json<ContactSearchResponse>> {
+    let result = saved_search_service::contacts(
+        &state.pool,
+        state.config.phone_region,
+        &user.username,
+        id,
+        paging,
+    )
+    .await?;
+    Ok(Json(result))
+}
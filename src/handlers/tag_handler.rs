@@ -0,0 +1,70 @@
+use axum::{
+    extract::{Extension, Path, State},
+    response::Response,
+    Json,
+};
+use std::sync::Arc;
+use crate::{
+    database::AppState,
+    errors::AppResult,
+    etag,
+    models::*,
+    services::tag_service,
+    validation::validate_request,
+};
+
+pub async fn create(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<CreateTagRequest>,
+) -> AppResult<Json<ApiResponse<TagResponse>>> {
+    validate_request(&req)?;
+    let tag = tag_service::create(&state.pool, &user.username, req).await?;
+    Ok(Json(ApiResponse { data: tag }))
+}
+
+pub async fn list(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> AppResult<Json<ApiResponse<Vec<TagResponse>>>> {
+    let tags = tag_service::list(&state.pool, &user.username).await?;
+    Ok(Json(ApiResponse { data: tags }))
+}
+
+pub async fn update(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateTagRequest>,
+) -> AppResult<Json<ApiResponse<TagResponse>>> {
+    validate_request(&req)?;
+    let tag = tag_service::update(&state.pool, &user.username, id, req).await?;
+    Ok(Json(ApiResponse { data: tag }))
+}
+
+pub async fn remove(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<String>>> {
+    tag_service::remove(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: "OK".to_string() }))
+}
+
+pub async fn attach(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path((contact_id, tag_id)): Path<(i32, i32)>,
+) -> AppResult<Response> {
+    let contact = tag_service::attach(&state.pool, &user.username, contact_id, tag_id).await?;
+    Ok(etag::tagged(contact.version, Json(ApiResponse { data: contact })))
+}
+
+pub async fn detach(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path((contact_id, tag_id)): Path<(i32, i32)>,
+) -> AppResult<Response> {
+    let contact = tag_service::detach(&state.pool, &user.username, contact_id, tag_id).await?;
+    Ok(etag::tagged(contact.version, Json(ApiResponse { data: contact })))
+}
@@ -0,0 +1,19 @@
+use axum::{
+    extract::{Extension, State},
+    Json,
+};
+use std::sync::Arc;
+use crate::{
+    database::AppState,
+    errors::AppResult,
+    models::*,
+    services::trash_service,
+};
+
+pub async fn list(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> AppResult<Json<ApiResponse<TrashResponse>>> {
+    let trash = trash_service::list(&state.pool, &user.username).await?;
+    Ok(Json(ApiResponse { data: trash }))
+}
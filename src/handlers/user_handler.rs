@@ -5,7 +5,7 @@ use axum::{
 use std::sync::Arc;
 use crate::{
     database::AppState,
-    errors::{AppError, AppResult},
+    errors::AppResult,
     models::*,
     services::user_service,
     validation::validate_request,
@@ -32,12 +32,7 @@ pub async fn login(
 pub async fn get_current(
     Extension(user): Extension<User>,
 ) -> AppResult<Json<ApiResponse<UserResponse>>> {
-    Ok(Json(ApiResponse {
-        data: UserResponse {
-            username: user.username,
-            name: user.name,
-        },
-    }))
+    Ok(Json(ApiResponse { data: user.into() }))
 }
 
 pub async fn update(
@@ -0,0 +1,82 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    Json,
+};
+use std::sync::Arc;
+use crate::{
+    database::AppState,
+    errors::AppResult,
+    models::*,
+    services::webhook_service,
+    validation::validate_request,
+};
+
+pub async fn create(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Json(req): Json<WebhookRequest>,
+) -> AppResult<Json<ApiResponse<WebhookResponse>>> {
+    validate_request(&req)?;
+    let webhook = webhook_service::create(
+        &state.pool,
+        state.config.webhook_allow_private_urls,
+        &user.username,
+        req,
+    )
+    .await?;
+    Ok(Json(ApiResponse { data: webhook }))
+}
+
+pub async fn list(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+) -> AppResult<Json<ApiResponse<Vec<WebhookResponse>>>> {
+    let webhooks = webhook_service::list(&state.pool, &user.username).await?;
+    Ok(Json(ApiResponse { data: webhooks }))
+}
+
+pub async fn get(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<WebhookResponse>>> {
+    let webhook = webhook_service::get(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: webhook }))
+}
+
+pub async fn update(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    Json(req): Json<WebhookRequest>,
+) -> AppResult<Json<ApiResponse<WebhookResponse>>> {
+    validate_request(&req)?;
+    let webhook = webhook_service::update(
+        &state.pool,
+        state.config.webhook_allow_private_urls,
+        &user.username,
+        id,
+        req,
+    )
+    .await?;
+    Ok(Json(ApiResponse { data: webhook }))
+}
+
+pub async fn remove(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+) -> AppResult<Json<ApiResponse<String>>> {
+    webhook_service::remove(&state.pool, &user.username, id).await?;
+    Ok(Json(ApiResponse { data: "OK".to_string() }))
+}
+
+pub async fn deliveries(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<User>,
+    Path(id): Path<i32>,
+    Query(req): Query<WebhookDeliveriesRequest>,
+) -> AppResult<Json<WebhookDeliveriesResponse>> {
+    let result = webhook_service::deliveries(&state.pool, &user.username, id, req).await?;
+    Ok(Json(result))
+}
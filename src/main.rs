@@ -1,10 +1,19 @@
 mod models;
+mod config;
 mod handlers;
 mod services;
 mod middleware;
 mod errors;
 mod database;
 mod validation;
+mod phone;
+mod vcard;
+mod search_query;
+mod countries;
+mod geocoder;
+mod address_format;
+mod etag;
+mod merge_patch;
 
 use axum::{
     Router,
@@ -16,9 +25,11 @@ use std::sync::Arc;
 use tower_http::{trace::TraceLayer, cors::{CorsLayer, AllowOrigin}};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use crate::config::Config;
 use crate::database::AppState;
+use crate::geocoder::PostalCodeGeocoder;
 use crate::handlers::*;
-use crate::middleware::auth_middleware;
+use crate::middleware::{auth_middleware, request_id_middleware};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -50,7 +61,57 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .run(&pool)
         .await?;
 
-    let state = Arc::new(AppState { pool });
+    let config = Config::from_env();
+
+    let normalized = services::contact_service::normalize_phones(&pool, config.phone_region).await?;
+    let normalized_countries = services::address_service::normalize_countries(&pool).await?;
+    if normalized > 0 {
+        tracing::info!("Normalized {} stored phone numbers", normalized);
+    }
+    if normalized_countries > 0 {
+        tracing::info!("Normalized {} stored address countries", normalized_countries);
+    }
+
+    let geocoder = match PostalCodeGeocoder::from_file(&config.geocoder_csv) {
+        Ok(geocoder) => geocoder,
+        Err(e) => {
+            tracing::warn!("Geocoding is disabled, postal codes could not be loaded: {}", e);
+            PostalCodeGeocoder::empty()
+        }
+    };
+
+    // Empty the trash of expired items every hour
+    let purge_pool = pool.clone();
+    let retention_days = config.trash_retention_days;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            match services::trash_service::purge(&purge_pool, retention_days).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("Purged {} items from the trash", purged),
+                Err(e) => tracing::error!("Could not purge the trash: {}", e),
+            }
+        }
+    });
+
+    // Send queued webhook events every few seconds
+    let webhook_pool = pool.clone();
+    let allow_private = config.webhook_allow_private_urls;
+    tokio::spawn(async move {
+        let client = services::webhook_service::http_client(allow_private);
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(5));
+        loop {
+            interval.tick().await;
+            match services::webhook_service::dispatch(&webhook_pool, &client, allow_private).await {
+                Ok(0) => {}
+                Ok(sent) => tracing::info!("Attempted {} webhook deliveries", sent),
+                Err(e) => tracing::error!("Could not dispatch webhooks: {}", e),
+            }
+        }
+    });
+
+    let state = Arc::new(AppState { pool, config, geocoder: Arc::new(geocoder) });
 
     // Public routes
     let public_routes = Router::new()
@@ -65,14 +126,60 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .route("/api/users/logout", delete(user_handler::logout))
         .route("/api/contacts", post(contact_handler::create))
         .route("/api/contacts", get(contact_handler::search))
+        .route("/api/contacts/import/vcard", post(contact_handler::import_vcard))
+        .route("/api/contacts/duplicates", get(contact_handler::duplicates))
+        .route("/api/contacts/batch", post(contact_handler::batch))
         .route("/api/contacts/:id", get(contact_handler::get))
         .route("/api/contacts/:id", put(contact_handler::update))
+        .route("/api/contacts/:id", patch(contact_handler::patch))
         .route("/api/contacts/:id", delete(contact_handler::remove))
+        .route("/api/contacts/:id/merge", post(contact_handler::merge))
+        .route("/api/contacts/:id/restore", post(contact_handler::restore))
+        .route("/api/contacts/:id/history", get(contact_handler::history))
+        .route("/api/contacts/:id/revisions", get(contact_handler::revisions))
+        .route("/api/contacts/:id/revisions/:rev", get(contact_handler::revision))
+        .route("/api/contacts/:id/revisions/:rev/revert", post(contact_handler::revert))
+        .route("/api/contacts/:id/tags/:tag_id", put(tag_handler::attach))
+        .route("/api/contacts/:id/tags/:tag_id", delete(tag_handler::detach))
         .route("/api/contacts/:contact_id/addresses", post(address_handler::create))
         .route("/api/contacts/:contact_id/addresses", get(address_handler::list))
         .route("/api/contacts/:contact_id/addresses/:id", get(address_handler::get))
         .route("/api/contacts/:contact_id/addresses/:id", put(address_handler::update))
+        .route("/api/contacts/:contact_id/addresses/:id", patch(address_handler::patch))
         .route("/api/contacts/:contact_id/addresses/:id", delete(address_handler::remove))
+        .route("/api/contacts/:contact_id/addresses/:id/restore", post(address_handler::restore))
+        .route("/api/addresses", get(address_handler::search))
+        .route("/api/addresses/nearby", get(address_handler::nearby))
+        .route("/api/address-books", post(address_book_handler::create))
+        .route("/api/address-books", get(address_book_handler::list))
+        .route("/api/address-books/:id", get(address_book_handler::get))
+        .route("/api/address-books/:id", put(address_book_handler::update))
+        .route("/api/address-books/:id", delete(address_book_handler::remove))
+        .route("/api/address-books/:id/members", get(address_book_handler::list_members))
+        .route("/api/address-books/:id/members/:username", put(address_book_handler::set_member))
+        .route("/api/address-books/:id/members/:username", delete(address_book_handler::remove_member))
+        .route("/api/trash", get(trash_handler::list))
+        .route("/api/audit", get(audit_handler::search))
+        .route("/api/tags", post(tag_handler::create))
+        .route("/api/tags", get(tag_handler::list))
+        .route("/api/tags/:id", put(tag_handler::update))
+        .route("/api/tags/:id", delete(tag_handler::remove))
+        .route("/api/custom-fields", post(custom_field_handler::create))
+        .route("/api/custom-fields", get(custom_field_handler::list))
+        .route("/api/custom-fields/:id", put(custom_field_handler::update))
+        .route("/api/custom-fields/:id", delete(custom_field_handler::remove))
+        .route("/api/saved-searches", post(saved_search_handler::create))
+        .route("/api/saved-searches", get(saved_search_handler::list))
+        .route("/api/saved-searches/:id", get(saved_search_handler::get))
+        .route("/api/saved-searches/:id", put(saved_search_handler::update))
+        .route("/api/saved-searches/:id", delete(saved_search_handler::remove))
+        .route("/api/saved-searches/:id/contacts", get(saved_search_handler::contacts))
+        .route("/api/webhooks", post(webhook_handler::create))
+        .route("/api/webhooks", get(webhook_handler::list))
+        .route("/api/webhooks/:id", get(webhook_handler::get))
+        .route("/api/webhooks/:id", put(webhook_handler::update))
+        .route("/api/webhooks/:id", delete(webhook_handler::remove))
+        .route("/api/webhooks/:id/deliveries", get(webhook_handler::deliveries))
         .layer(axum::middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -89,12 +196,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .allow_headers([
             HeaderName::from_static("content-type"),
             HeaderName::from_static("authorization"),
-        ]);
+            HeaderName::from_static("x-request-id"),
+            HeaderName::from_static("if-match"),
+            HeaderName::from_static("if-none-match"),
+        ])
+        .expose_headers([HeaderName::from_static("x-request-id"), HeaderName::from_static("etag")]);
 
     // Combine routes
     let app = Router::new()
         .merge(public_routes)
         .merge(protected_routes)
+        .layer(axum::middleware::from_fn(request_id_middleware))
         .layer(TraceLayer::new_for_http())
         .layer(cors) // ✅ CORS diaktifkan di sini!
         .with_state(state);
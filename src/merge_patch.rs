@@ -0,0 +1,36 @@
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use crate::errors::{AppError, AppResult};
+
+// Applies an RFC 7396 JSON merge patch: members set to null are removed,
+// objects are merged member by member and anything else replaces the target.
+pub fn apply(target: &mut Value, patch: &Value) {
+    let Value::Object(members) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(target) = target {
+        for (name, value) in members {
+            if value.is_null() {
+                target.remove(name);
+            } else {
+                apply(target.entry(name.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+// Patches `document` and reads the result back as a full update request,
+// which the caller validates like a PUT body
+pub fn merge<T: DeserializeOwned>(mut document: Value, patch: &Value) -> AppResult<T> {
+    if !patch.is_object() {
+        return Err(AppError::BadRequest("merge patch must be a JSON object".to_string()));
+    }
+
+    apply(&mut document, patch);
+    serde_json::from_value(document).map_err(|e| AppError::BadRequest(format!("patched resource is invalid: {}", e)))
+}
@@ -1,11 +1,43 @@
 use axum::{
     extract::{Request, State},
-    http::HeaderMap,
+    http::{HeaderMap, HeaderValue},
     middleware::Next,
     response::Response,
 };
 use std::sync::Arc;
-use crate::{database::AppState, errors::AppError, models::User};
+use crate::{database::AppState, errors::AppError, models::User, services::user_service};
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+// Id of the request being handled, recorded with audit entries
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+// Takes the caller's `X-Request-Id` when it looks sane, otherwise makes one
+// up, and echoes it back on the response
+pub async fn request_id_middleware(req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get("X-Request-Id")
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 64
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let mut response = REQUEST_ID.scope(request_id.clone(), next.run(req)).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert("X-Request-Id", value);
+    }
+
+    response
+}
 
 pub async fn auth_middleware(
     State(state): State<Arc<AppState>>,
@@ -15,9 +47,10 @@ pub async fn auth_middleware(
     let headers = req.headers();
     let token = get_token_from_headers(headers)?;
 
-    let user = sqlx::query_as::<_, User>(
-        "SELECT username, password, name, token FROM users WHERE token = ?"
-    )
+    let user = sqlx::query_as::<_, User>(&format!(
+        "SELECT {} FROM users WHERE token = ?",
+        user_service::USER_COLUMNS
+    ))
     .bind(token)
     .fetch_optional(&state.pool)
     .await?
@@ -1,5 +1,12 @@
+use std::collections::{BTreeMap, HashMap};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
+use crate::{address_format, countries};
+use crate::validation::{
+    validate_address_type, validate_contact_label, validate_country, validate_create_address,
+    validate_update_address,
+};
 
 // User Models
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -8,6 +15,9 @@ pub struct User {
     pub password: String,
     pub name: String,
     pub token: Option<String>,
+    pub is_admin: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -40,6 +50,8 @@ pub struct UpdateUserRequest {
 pub struct UserResponse {
     pub username: String,
     pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
@@ -55,7 +67,51 @@ pub struct Contact {
     pub last_name: Option<String>,
     pub email: Option<String>,
     pub phone: Option<String>,
+    pub phone_e164: Option<String>,
     pub username: String,
+    pub address_book_id: i32,
+    pub version: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ContactEmail {
+    pub id: i32,
+    pub contact_id: i32,
+    pub email: String,
+    pub label: String,
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ContactPhone {
+    pub id: i32,
+    pub contact_id: i32,
+    pub phone: String,
+    pub e164: Option<String>,
+    pub label: String,
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ContactEmailRequest {
+    #[validate(email, length(max = 200))]
+    pub email: String,
+    #[validate(custom = "validate_contact_label")]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ContactPhoneRequest {
+    #[validate(length(min = 1, max = 20))]
+    pub phone: String,
+    #[validate(custom = "validate_contact_label")]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub is_primary: bool,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -68,6 +124,14 @@ pub struct CreateContactRequest {
     pub email: Option<String>,
     #[validate(length(max = 20))]
     pub phone: Option<String>,
+    #[validate]
+    pub emails: Option<Vec<ContactEmailRequest>>,
+    #[validate]
+    pub phones: Option<Vec<ContactPhoneRequest>>,
+    // Defaults to the caller's own address book
+    pub address_book_id: Option<i32>,
+    // Values keyed by custom field name
+    pub custom_fields: Option<HashMap<String, serde_json::Value>>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -80,30 +144,357 @@ pub struct UpdateContactRequest {
     pub email: Option<String>,
     #[validate(length(max = 20))]
     pub phone: Option<String>,
+    #[validate]
+    pub emails: Option<Vec<ContactEmailRequest>>,
+    #[validate]
+    pub phones: Option<Vec<ContactPhoneRequest>>,
+    pub custom_fields: Option<HashMap<String, serde_json::Value>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchContactRequest {
     pub name: Option<String>,
     pub email: Option<String>,
     pub phone: Option<String>,
+    // Comma separated tag names, matched per `tag_mode`
+    pub tag: Option<String>,
+    pub tag_mode: Option<TagMode>,
+    pub address_book_id: Option<i32>,
+    // Comma separated `field:value` pairs, e.g. `company:Acme,vip:true`
+    pub custom: Option<String>,
+    // Search query with field qualifiers, see `search_query`
+    pub q: Option<String>,
+    // Only contacts changed at or after this time, for incremental sync
+    pub updated_since: Option<DateTime<Utc>>,
+    // Comma separated sort keys, e.g. `last_name,first_name`
+    pub sort: Option<String>,
+    // Comma separated `asc` / `desc`, matched to the sort keys by position
+    pub order: Option<String>,
+    // `next_cursor` of the previous page, replaces `page`
+    pub cursor: Option<String>,
+    // Set to false to skip counting the matching contacts
+    pub include_total: Option<bool>,
     pub page: Option<i32>,
     pub size: Option<i32>,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMode {
+    #[default]
+    Any,
+    All,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContactEmailResponse {
+    pub id: i32,
+    pub email: String,
+    pub label: String,
+    pub is_primary: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContactPhoneResponse {
+    pub id: i32,
+    pub phone: String,
+    pub e164: Option<String>,
+    pub label: String,
+    pub is_primary: bool,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ContactResponse {
     pub id: i32,
+    pub address_book_id: i32,
     pub first_name: String,
     pub last_name: Option<String>,
     pub email: Option<String>,
     pub phone: Option<String>,
+    pub phone_e164: Option<String>,
+    pub emails: Vec<ContactEmailResponse>,
+    pub phones: Vec<ContactPhoneResponse>,
+    pub tags: Vec<TagResponse>,
+    pub custom_fields: BTreeMap<String, serde_json::Value>,
+    pub primary_address: Option<AddressResponse>,
+    pub version: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    // Only set on full-text (`q`) search results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched_fields: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ContactSearchResponse {
     pub data: Vec<ContactResponse>,
-    pub paging: PagingResponse,
+    // Left out when `include_total=false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paging: Option<PagingResponse>,
+    // Pass as `cursor` to fetch the next page, null on the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroupResponse {
+    pub reasons: Vec<String>,
+    pub contacts: Vec<ContactResponse>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeSide {
+    #[default]
+    Target,
+    Source,
+}
+
+// Which contact each field is taken from; emails, phones, tags and
+// addresses of both contacts are always kept.
+#[derive(Debug, Default, Deserialize)]
+pub struct MergeFields {
+    #[serde(default)]
+    pub first_name: MergeSide,
+    #[serde(default)]
+    pub last_name: MergeSide,
+    #[serde(default)]
+    pub email: MergeSide,
+    #[serde(default)]
+    pub phone: MergeSide,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeContactRequest {
+    pub source_id: i32,
+    #[serde(default)]
+    pub fields: MergeFields,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportContactsRequest {
+    pub address_book_id: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportIssue {
+    pub entry: usize,
+    pub name: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContactImportResponse {
+    pub imported: usize,
+    pub skipped: usize,
+    pub contacts: Vec<ContactResponse>,
+    pub issues: Vec<ImportIssue>,
+}
+
+// One change in a batch, picked by its `op`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOperation {
+    Create {
+        data: CreateContactRequest,
+    },
+    // `version` is checked like an If-Match header
+    Update {
+        id: i32,
+        data: UpdateContactRequest,
+        version: Option<i32>,
+    },
+    Delete {
+        id: i32,
+        version: Option<i32>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchContactRequest {
+    // Applies all operations or none of them
+    #[serde(default)]
+    pub atomic: bool,
+    pub operations: Vec<BatchOperation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchOperationResult {
+    pub index: usize,
+    // Status the operation would have had as a single request
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<ContactResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchContactResponse {
+    // False when an atomic batch was rolled back
+    pub committed: bool,
+    pub results: Vec<BatchOperationResult>,
+}
+
+// Address Book Models
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookRole {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AddressBook {
+    pub id: i32,
+    pub name: String,
+    pub username: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateAddressBookRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateAddressBookRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: BookRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressBookResponse {
+    pub id: i32,
+    pub name: String,
+    pub is_default: bool,
+    pub role: BookRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressBookMemberResponse {
+    pub username: String,
+    pub name: String,
+    pub role: BookRole,
+}
+
+// Custom Field Models
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomFieldType {
+    Text,
+    Number,
+    Date,
+    Bool,
+    Enum,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CustomField {
+    pub id: i32,
+    pub name: String,
+    pub field_type: String,
+    pub required: bool,
+    pub options: Option<String>,
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCustomFieldRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub name: String,
+    pub field_type: CustomFieldType,
+    #[serde(default)]
+    pub required: bool,
+    // Allowed values of an enum field
+    pub options: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateCustomFieldRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub name: String,
+    #[serde(default)]
+    pub required: bool,
+    pub options: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CustomFieldResponse {
+    pub id: i32,
+    pub name: String,
+    pub field_type: CustomFieldType,
+    pub required: bool,
+    pub options: Vec<String>,
+}
+
+// Saved Search Models
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SavedSearch {
+    pub id: i32,
+    pub name: String,
+    pub filters: String,
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SavedSearchRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    // Paging fields are not saved
+    pub filters: SearchContactRequest,
+}
+
+// Paging for running a saved search
+#[derive(Debug, Deserialize)]
+pub struct SavedSearchContactsRequest {
+    pub cursor: Option<String>,
+    pub include_total: Option<bool>,
+    pub page: Option<i32>,
+    pub size: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SavedSearchResponse {
+    pub id: i32,
+    pub name: String,
+    pub filters: serde_json::Value,
+}
+
+// Tag Models
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateTagRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateTagRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagResponse {
+    pub id: i32,
+    pub name: String,
 }
 
 // Address Models
@@ -115,10 +506,18 @@ pub struct Address {
     pub province: Option<String>,
     pub country: String,
     pub postal_code: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
     pub contact_id: i32,
+    pub address_type: String,
+    pub is_primary: bool,
+    pub version: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_create_address"))]
 pub struct CreateAddressRequest {
     #[validate(length(max = 255))]
     pub street: Option<String>,
@@ -126,13 +525,22 @@ pub struct CreateAddressRequest {
     pub city: Option<String>,
     #[validate(length(max = 100))]
     pub province: Option<String>,
-    #[validate(length(min = 1, max = 100))]
+    // ISO 3166-1 code or English name, stored as the alpha-2 code
+    #[validate(length(min = 1, max = 100), custom = "validate_country")]
     pub country: String,
     #[validate(length(min = 1, max = 10))]
     pub postal_code: String,
+    // Defaults to `other`
+    #[serde(rename = "type")]
+    #[validate(custom = "validate_address_type")]
+    pub address_type: Option<String>,
+    // At most one address per contact is primary
+    #[serde(default)]
+    pub is_primary: bool,
 }
 
 #[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validate_update_address"))]
 pub struct UpdateAddressRequest {
     #[validate(length(max = 255))]
     pub street: Option<String>,
@@ -140,10 +548,18 @@ pub struct UpdateAddressRequest {
     pub city: Option<String>,
     #[validate(length(max = 100))]
     pub province: Option<String>,
-    #[validate(length(min = 1, max = 100))]
+    // ISO 3166-1 code or English name, stored as the alpha-2 code
+    #[validate(length(min = 1, max = 100), custom = "validate_country")]
     pub country: String,
     #[validate(length(min = 1, max = 10))]
     pub postal_code: String,
+    // Defaults to `other`
+    #[serde(rename = "type")]
+    #[validate(custom = "validate_address_type")]
+    pub address_type: Option<String>,
+    // At most one address per contact is primary
+    #[serde(default)]
+    pub is_primary: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -153,13 +569,309 @@ pub struct AddressResponse {
     pub city: Option<String>,
     pub province: Option<String>,
     pub country: String,
+    // None for countries stored before codes were validated
+    pub country_name: Option<String>,
     pub postal_code: String,
+    // Filled in by the geocoder shortly after the address is saved
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    #[serde(rename = "type")]
+    pub address_type: String,
+    pub is_primary: bool,
+    // Mailing label layout for the address's country
+    pub formatted: String,
+    pub version: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressFormat {
+    #[default]
+    Label,
+    SingleLine,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetAddressRequest {
+    // Layout of `formatted`, defaults to `label`
+    pub format: Option<AddressFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchAddressRequest {
+    pub city: Option<String>,
+    pub province: Option<String>,
+    pub country: Option<String>,
+    // Matches postal codes starting with the value
+    pub postal_code: Option<String>,
+    pub page: Option<i32>,
+    pub size: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressContactResponse {
+    pub id: i32,
+    pub first_name: String,
+    pub last_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContactAddressResponse {
+    #[serde(flatten)]
+    pub address: AddressResponse,
+    pub contact: AddressContactResponse,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressSearchResponse {
+    pub data: Vec<ContactAddressResponse>,
+    pub paging: PagingResponse,
+}
+
+// Trash Models
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TrashedContactResponse {
+    pub id: i32,
+    pub first_name: String,
+    pub last_name: Option<String>,
+    pub address_book_id: i32,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashedAddressResponse {
+    #[serde(flatten)]
+    pub address: ContactAddressResponse,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashResponse {
+    pub contacts: Vec<TrashedContactResponse>,
+    pub addresses: Vec<TrashedAddressResponse>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct NearbyAddressRequest {
+    #[validate(range(min = -90.0, max = 90.0))]
+    pub lat: f64,
+    #[validate(range(min = -180.0, max = 180.0))]
+    pub lng: f64,
+    // Defaults to 10 km
+    #[validate(range(min = 0.0, max = 500.0))]
+    pub radius_km: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NearbyAddressResponse {
+    #[serde(flatten)]
+    pub address: ContactAddressResponse,
+    pub distance_km: f64,
+}
+
+// Revision Models
+// State of a contact and its addresses as stored with each revision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContactSnapshot {
+    pub first_name: String,
+    pub last_name: Option<String>,
+    pub emails: Vec<ContactEmailRequest>,
+    pub phones: Vec<ContactPhoneRequest>,
+    pub custom_fields: BTreeMap<String, serde_json::Value>,
+    pub addresses: Vec<AddressSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressSnapshot {
+    pub id: i32,
+    #[serde(flatten)]
+    pub address: CreateAddressRequest,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ContactRevision {
+    pub revision: i32,
+    pub snapshot: String,
+    pub username: String,
+    pub reverted_from: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContactRevisionResponse {
+    pub revision: i32,
+    pub username: String,
+    // Revision this one restored, for reverts
+    pub reverted_from: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    // Only included when getting a single revision
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<ContactSnapshot>,
+}
+
+// Audit Models
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Merge,
+    Revert,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: String,
+    pub entity: String,
+    pub entity_id: String,
+    pub contact_id: Option<i32>,
+    pub action: String,
+    pub changes: String,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContactHistoryRequest {
+    pub page: Option<i32>,
+    pub size: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchAuditRequest {
+    pub actor: Option<String>,
+    // `contact`, `address` or `user`
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub action: Option<AuditAction>,
+    pub request_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub page: Option<i32>,
+    pub size: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEntryResponse {
+    pub id: i64,
+    pub actor: String,
+    pub entity: String,
+    pub entity_id: String,
+    pub contact_id: Option<i32>,
+    pub action: String,
+    // Changed fields as `{ "before": {...}, "after": {...} }`
+    pub changes: serde_json::Value,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogResponse {
+    pub data: Vec<AuditEntryResponse>,
+    pub paging: PagingResponse,
+}
+
+// Webhook Models
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "contact.created")]
+    ContactCreated,
+    #[serde(rename = "contact.updated")]
+    ContactUpdated,
+    #[serde(rename = "contact.deleted")]
+    ContactDeleted,
+    #[serde(rename = "contact.restored")]
+    ContactRestored,
+    #[serde(rename = "address.created")]
+    AddressCreated,
+    #[serde(rename = "address.updated")]
+    AddressUpdated,
+    #[serde(rename = "address.deleted")]
+    AddressDeleted,
+    #[serde(rename = "address.restored")]
+    AddressRestored,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    // Comma separated event names
+    pub events: String,
+    pub secret: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct WebhookRequest {
+    #[validate(url, length(max = 2048))]
+    pub url: String,
+    #[validate(length(min = 1))]
+    pub events: Vec<WebhookEvent>,
+    // Generated when left out on create, kept when left out on update
+    #[validate(length(min = 16, max = 100))]
+    pub secret: Option<String>,
+    // Inactive webhooks get no deliveries; defaults to active on create
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    // Only returned when it is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct WebhookDeliveryResponse {
+    pub id: i64,
+    // Same for every webhook the event went to, see `X-Webhook-Id`
+    pub event_id: i64,
+    pub event: String,
+    pub contact_id: i32,
+    // `pending`, `delivered` or `failed`
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookDeliveriesRequest {
+    // `pending`, `delivered` or `failed`
+    pub status: Option<String>,
+    pub page: Option<i32>,
+    pub size: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookDeliveriesResponse {
+    pub data: Vec<WebhookDeliveryResponse>,
+    pub paging: PagingResponse,
 }
 
 // Common Models
 #[derive(Debug, Serialize)]
 pub struct PagingResponse {
-    pub page: i32,
+    // Left out when paging with a cursor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<i32>,
     pub total_page: i32,
     pub total_item: i64,
 }
@@ -178,23 +890,230 @@ impl From<Contact> for ContactResponse {
     fn from(contact: Contact) -> Self {
         Self {
             id: contact.id,
+            address_book_id: contact.address_book_id,
             first_name: contact.first_name,
             last_name: contact.last_name,
             email: contact.email,
             phone: contact.phone,
+            phone_e164: contact.phone_e164,
+            emails: Vec::new(),
+            phones: Vec::new(),
+            tags: Vec::new(),
+            custom_fields: BTreeMap::new(),
+            primary_address: None,
+            version: contact.version,
+            created_at: contact.created_at,
+            updated_at: contact.updated_at,
+            score: None,
+            matched_fields: None,
+        }
+    }
+}
+
+impl From<ContactEmail> for ContactEmailResponse {
+    fn from(email: ContactEmail) -> Self {
+        Self {
+            id: email.id,
+            email: email.email,
+            label: email.label,
+            is_primary: email.is_primary,
+        }
+    }
+}
+
+impl From<ContactPhone> for ContactPhoneResponse {
+    fn from(phone: ContactPhone) -> Self {
+        Self {
+            id: phone.id,
+            phone: phone.phone,
+            e164: phone.e164,
+            label: phone.label,
+            is_primary: phone.is_primary,
         }
     }
 }
 
 impl From<Address> for AddressResponse {
     fn from(address: Address) -> Self {
-        Self {
+        let mut response = Self {
             id: address.id,
             street: address.street,
             city: address.city,
             province: address.province,
+            country_name: countries::name(&address.country).map(str::to_string),
             country: address.country,
             postal_code: address.postal_code,
+            latitude: address.latitude,
+            longitude: address.longitude,
+            address_type: address.address_type,
+            is_primary: address.is_primary,
+            formatted: String::new(),
+            version: address.version,
+            created_at: address.created_at,
+            updated_at: address.updated_at,
+        };
+        response.formatted = address_format::format(&response, AddressFormat::Label);
+        response
+    }
+}
+
+impl AddressFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AddressFormat::Label => "label",
+            AddressFormat::SingleLine => "single_line",
+        }
+    }
+}
+
+impl BookRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookRole::Viewer => "viewer",
+            BookRole::Editor => "editor",
+            BookRole::Owner => "owner",
+        }
+    }
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Merge => "merge",
+            AuditAction::Revert => "revert",
+        }
+    }
+}
+
+impl From<AuditEntry> for AuditEntryResponse {
+    fn from(entry: AuditEntry) -> Self {
+        Self {
+            id: entry.id,
+            actor: entry.actor,
+            entity: entry.entity,
+            entity_id: entry.entity_id,
+            contact_id: entry.contact_id,
+            action: entry.action,
+            changes: serde_json::from_str(&entry.changes).unwrap_or(serde_json::Value::Null),
+            request_id: entry.request_id,
+            created_at: entry.created_at,
+        }
+    }
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::ContactCreated => "contact.created",
+            WebhookEvent::ContactUpdated => "contact.updated",
+            WebhookEvent::ContactDeleted => "contact.deleted",
+            WebhookEvent::ContactRestored => "contact.restored",
+            WebhookEvent::AddressCreated => "address.created",
+            WebhookEvent::AddressUpdated => "address.updated",
+            WebhookEvent::AddressDeleted => "address.deleted",
+            WebhookEvent::AddressRestored => "address.restored",
+        }
+    }
+}
+
+impl From<Webhook> for WebhookResponse {
+    fn from(webhook: Webhook) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            events: webhook.events.split(',').map(str::to_string).collect(),
+            active: webhook.active,
+            secret: None,
+            created_at: webhook.created_at,
+            updated_at: webhook.updated_at,
+        }
+    }
+}
+
+impl std::str::FromStr for BookRole {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "viewer" => Ok(BookRole::Viewer),
+            "editor" => Ok(BookRole::Editor),
+            "owner" => Ok(BookRole::Owner),
+            other => Err(format!("unknown address book role {}", other)),
+        }
+    }
+}
+
+impl CustomFieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CustomFieldType::Text => "text",
+            CustomFieldType::Number => "number",
+            CustomFieldType::Date => "date",
+            CustomFieldType::Bool => "bool",
+            CustomFieldType::Enum => "enum",
+        }
+    }
+}
+
+impl std::str::FromStr for CustomFieldType {
+    type Err = String;
+
+    fn from_str(field_type: &str) -> Result<Self, Self::Err> {
+        match field_type {
+            "text" => Ok(CustomFieldType::Text),
+            "number" => Ok(CustomFieldType::Number),
+            "date" => Ok(CustomFieldType::Date),
+            "bool" => Ok(CustomFieldType::Bool),
+            "enum" => Ok(CustomFieldType::Enum),
+            other => Err(format!("unknown custom field type {}", other)),
+        }
+    }
+}
+
+impl CustomField {
+    pub fn kind(&self) -> CustomFieldType {
+        self.field_type.parse().unwrap_or(CustomFieldType::Text)
+    }
+
+    pub fn option_list(&self) -> Vec<String> {
+        self.options
+            .as_deref()
+            .and_then(|options| serde_json::from_str(options).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl From<CustomField> for CustomFieldResponse {
+    fn from(field: CustomField) -> Self {
+        Self {
+            id: field.id,
+            field_type: field.kind(),
+            options: field.option_list(),
+            name: field.name,
+            required: field.required,
+        }
+    }
+}
+
+impl From<SavedSearch> for SavedSearchResponse {
+    fn from(search: SavedSearch) -> Self {
+        Self {
+            id: search.id,
+            name: search.name,
+            filters: serde_json::from_str(&search.filters).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<Tag> for TagResponse {
+    fn from(tag: Tag) -> Self {
+        Self {
+            id: tag.id,
+            name: tag.name,
         }
     }
 }
@@ -204,6 +1123,8 @@ impl From<User> for UserResponse {
         Self {
             username: user.username,
             name: user.name,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use phonenumber::{country, metadata::DATABASE, Mode};
+
+// Parses a phone number written in international or national format and
+// returns its E.164 form, or None when it isn't a valid number.
+pub fn to_e164(raw: &str, region: country::Id) -> Option<String> {
+    let number = phonenumber::parse(Some(region), raw).ok()?;
+    if !phonenumber::is_valid(&number) {
+        return None;
+    }
+
+    Some(number.format().mode(Mode::E164).to_string())
+}
+
+// Turns a possibly partial search term into the digits to look for in stored
+// E.164 numbers, so `0812` matches `+62812...` in region ID.
+pub fn search_digits(raw: &str, region: country::Id) -> String {
+    if let Some(e164) = to_e164(raw, region) {
+        return e164.trim_start_matches('+').to_string();
+    }
+
+    let digits: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
+    if raw.trim_start().starts_with('+') {
+        return digits;
+    }
+
+    let Some(metadata) = DATABASE.by_id(region.as_ref()) else {
+        return digits;
+    };
+
+    match metadata
+        .national_prefix()
+        .and_then(|prefix| digits.strip_prefix(prefix))
+    {
+        Some(national) => format!("{}{}", metadata.country_code(), national),
+        None => digits,
+    }
+}
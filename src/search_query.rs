@@ -0,0 +1,506 @@
+use phonenumber::country;
+use crate::{countries, errors::AppError, phone};
+
+// Columns covered by the FULLTEXT indexes, in index order
+pub const CONTACT_FULLTEXT: &str = "first_name, last_name, email, phone";
+pub const ADDRESS_FULLTEXT: &str = "a.street, a.city, a.province, a.country, a.postal_code";
+
+// Keeps user queries from growing into huge SQL statements
+const MAX_TERMS: usize = 20;
+const MAX_DEPTH: usize = 8;
+
+// Parsed `q` search, e.g. `city:Bandung -tag:inactive (budi OR "dewi s")`.
+// Terms next to each other must all match, `OR` matches either side, `-`
+// negates and `*` / `?` are wildcards in field values.
+#[derive(Debug)]
+pub enum Query {
+    Term(Term),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+#[derive(Debug)]
+pub struct Term {
+    // None searches the full-text indexes
+    pub field: Option<Field>,
+    pub value: String,
+    pub phrase: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Field {
+    Name,
+    FirstName,
+    LastName,
+    Email,
+    Phone,
+    Street,
+    City,
+    Province,
+    Country,
+    PostalCode,
+    Tag,
+}
+
+impl std::str::FromStr for Field {
+    type Err = AppError;
+
+    fn from_str(field: &str) -> Result<Self, Self::Err> {
+        match field.to_lowercase().as_str() {
+            "name" => Ok(Field::Name),
+            "first_name" => Ok(Field::FirstName),
+            "last_name" => Ok(Field::LastName),
+            "email" => Ok(Field::Email),
+            "phone" => Ok(Field::Phone),
+            "street" => Ok(Field::Street),
+            "city" => Ok(Field::City),
+            "province" => Ok(Field::Province),
+            "country" => Ok(Field::Country),
+            "postal_code" => Ok(Field::PostalCode),
+            "tag" => Ok(Field::Tag),
+            other => Err(AppError::Validation(format!("unknown search field {}", other))),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Token {
+    LParen,
+    RParen,
+    Or,
+    Not,
+    Term(Term),
+}
+
+pub fn parse(q: &str) -> Result<Query, AppError> {
+    let tokens = tokenize(q)?;
+
+    let terms = tokens.iter().filter(|t| matches!(t, Token::Term(_))).count();
+    if terms == 0 {
+        return Err(AppError::Validation("search query is empty".to_string()));
+    }
+    if terms > MAX_TERMS {
+        return Err(AppError::Validation(format!("search query has more than {} terms", MAX_TERMS)));
+    }
+
+    let mut parser = Parser { tokens: tokens.into_iter().peekable(), depth: 0 };
+    let query = parser.and()?;
+
+    if parser.tokens.next().is_some() {
+        return Err(AppError::Validation("search query has an unmatched )".to_string()));
+    }
+
+    Ok(query)
+}
+
+fn tokenize(q: &str) -> Result<Vec<Token>, AppError> {
+    let mut tokens = Vec::new();
+    let mut chars = q.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                let mut word = String::new();
+                let mut field = None;
+                let mut phrase = false;
+
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+
+                    if c == '"' && word.is_empty() {
+                        phrase = true;
+                        word = read_phrase(&mut chars)?;
+                        break;
+                    }
+                    if c == ':' && field.is_none() && !word.is_empty() {
+                        field = Some(word.parse::<Field>()?);
+                        word.clear();
+                        continue;
+                    }
+                    word.push(c);
+                }
+
+                if field.is_none() && !phrase && word == "OR" {
+                    tokens.push(Token::Or);
+                } else if word.is_empty() {
+                    return Err(AppError::Validation("search field has no value".to_string()));
+                } else {
+                    tokens.push(Token::Term(Term { field, value: word, phrase }));
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn read_phrase(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, AppError> {
+    let mut phrase = String::new();
+    for c in chars.by_ref() {
+        if c == '"' {
+            return Ok(phrase);
+        }
+        phrase.push(c);
+    }
+
+    Err(AppError::Validation("search query has an unterminated quote".to_string()))
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+    depth: usize,
+}
+
+impl Parser {
+    // and := or+
+    fn and(&mut self) -> Result<Query, AppError> {
+        let mut parts = Vec::new();
+        while !matches!(self.tokens.peek(), None | Some(Token::RParen)) {
+            parts.push(self.or()?);
+        }
+
+        match parts.len() {
+            0 => Err(AppError::Validation("search query has an empty group".to_string())),
+            1 => Ok(parts.remove(0)),
+            _ => Ok(Query::And(parts)),
+        }
+    }
+
+    // or := unary ("OR" unary)*
+    fn or(&mut self) -> Result<Query, AppError> {
+        let mut parts = vec![self.unary()?];
+        while matches!(self.tokens.peek(), Some(Token::Or)) {
+            self.tokens.next();
+            parts.push(self.unary()?);
+        }
+
+        Ok(if parts.len() == 1 { parts.remove(0) } else { Query::Or(parts) })
+    }
+
+    // unary := "-"? (term | "(" and ")")
+    fn unary(&mut self) -> Result<Query, AppError> {
+        match self.tokens.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.unary()?))),
+            Some(Token::Term(term)) => Ok(Query::Term(term)),
+            Some(Token::LParen) => {
+                self.depth += 1;
+                if self.depth > MAX_DEPTH {
+                    return Err(AppError::Validation("search query is nested too deeply".to_string()));
+                }
+                let query = self.and()?;
+                if !matches!(self.tokens.next(), Some(Token::RParen)) {
+                    return Err(AppError::Validation("search query has an unmatched (".to_string()));
+                }
+                self.depth -= 1;
+                Ok(query)
+            }
+            Some(Token::Or) => Err(AppError::Validation("OR needs a term on both sides".to_string())),
+            Some(Token::RParen) | None => {
+                Err(AppError::Validation("search query ends unexpectedly".to_string()))
+            }
+        }
+    }
+}
+
+impl Query {
+    // Compiles the query into a WHERE condition on `contacts` and the
+    // values to bind for its placeholders, in order.
+    pub fn to_sql(
+        &self,
+        region: country::Id,
+        username: &str,
+    ) -> Result<(String, Vec<String>), AppError> {
+        let mut params = Vec::new();
+        let sql = self.compile(region, username, &mut params)?;
+        Ok((sql, params))
+    }
+
+    fn compile(
+        &self,
+        region: country::Id,
+        username: &str,
+        params: &mut Vec<String>,
+    ) -> Result<String, AppError> {
+        match self {
+            Query::Term(term) => term.compile(region, username, params),
+            // A condition on an empty column is NULL rather than false, so
+            // `-last_name:smith` still matches contacts without a last name
+            Query::Not(query) => Ok(format!("NOT COALESCE({}, FALSE)", query.compile(region, username, params)?)),
+            Query::And(parts) | Query::Or(parts) => {
+                let joiner = if matches!(self, Query::And(_)) { " AND " } else { " OR " };
+                let compiled = parts
+                    .iter()
+                    .map(|part| part.compile(region, username, params))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("({})", compiled.join(joiner)))
+            }
+        }
+    }
+
+    // Boolean mode full-text terms that aren't negated, used to rank results
+    pub fn ranking_terms(&self) -> Vec<String> {
+        match self {
+            Query::Term(term) if term.field.is_none() => term.fulltext().into_iter().collect(),
+            Query::Term(_) | Query::Not(_) => Vec::new(),
+            Query::And(parts) | Query::Or(parts) => {
+                parts.iter().flat_map(Query::ranking_terms).collect()
+            }
+        }
+    }
+}
+
+impl Term {
+    fn compile(
+        &self,
+        region: country::Id,
+        username: &str,
+        params: &mut Vec<String>,
+    ) -> Result<String, AppError> {
+        let Some(field) = self.field else {
+            let term = self.fulltext().ok_or_else(|| {
+                AppError::Validation(format!("search term {} has no letters or digits", self.value))
+            })?;
+            params.push(term.clone());
+            params.push(term);
+            return Ok(format!(
+                "(MATCH({}) AGAINST (? IN BOOLEAN MODE)
+                 OR EXISTS (SELECT 1 FROM addresses a WHERE a.contact_id = contacts.id
+                 AND a.deleted_at IS NULL AND MATCH({}) AGAINST (? IN BOOLEAN MODE)))",
+                CONTACT_FULLTEXT, ADDRESS_FULLTEXT
+            ));
+        };
+
+        let pattern = self.like_pattern();
+        let address = |column: &str, params: &mut Vec<String>| {
+            params.push(pattern.clone());
+            format!(
+                "EXISTS (SELECT 1 FROM addresses a WHERE a.contact_id = contacts.id 
+                 AND a.deleted_at IS NULL AND a.{} LIKE ?)",
+                column
+            )
+        };
+
+        let sql = match field {
+            Field::Name => {
+                params.extend([pattern.clone(), pattern.clone(), pattern.clone()]);
+                "(first_name LIKE ? OR last_name LIKE ? OR CONCAT_WS(' ', first_name, last_name) LIKE ?)"
+                    .to_string()
+            }
+            Field::FirstName => {
+                params.push(pattern.clone());
+                "first_name LIKE ?".to_string()
+            }
+            Field::LastName => {
+                params.push(pattern.clone());
+                "last_name LIKE ?".to_string()
+            }
+            Field::Email => {
+                params.push(pattern.clone());
+                "EXISTS (SELECT 1 FROM contact_emails ce WHERE ce.contact_id = contacts.id AND ce.email LIKE ?)"
+                    .to_string()
+            }
+            // Phones match on normalized digits like the `phone` filter
+            Field::Phone => {
+                let value = self.value.replace(['*', '?'], "");
+                let digits = phone::search_digits(&value, region);
+                params.push(format!("%{}%", escape_like(&value)));
+                // Without digits the normalized number would match any phone
+                if digits.is_empty() {
+                    "EXISTS (SELECT 1 FROM contact_phones cp WHERE cp.contact_id = contacts.id AND cp.phone LIKE ?)"
+                        .to_string()
+                } else {
+                    params.push(format!("%{}%", digits));
+                    "EXISTS (SELECT 1 FROM contact_phones cp WHERE cp.contact_id = contacts.id
+                     AND (cp.phone LIKE ? OR REPLACE(cp.e164, '+', '') LIKE ?))"
+                        .to_string()
+                }
+            }
+            Field::Street => address("street", params),
+            Field::City => address("city", params),
+            Field::Province => address("province", params),
+            // Known countries match on their stored code
+            Field::Country => match countries::find(&self.value) {
+                Some(country) => {
+                    params.push(country.code.to_string());
+                    "EXISTS (SELECT 1 FROM addresses a WHERE a.contact_id = contacts.id 
+                     AND a.deleted_at IS NULL AND a.country = ?)"
+                        .to_string()
+                }
+                None => address("country", params),
+            },
+            Field::PostalCode => address("postal_code", params),
+            Field::Tag => {
+                params.push(username.to_string());
+                params.push(pattern.clone());
+                "EXISTS (SELECT 1 FROM contact_tags ct JOIN tags t ON t.id = ct.tag_id
+                 WHERE ct.contact_id = contacts.id AND t.username = ? AND t.name LIKE ?)"
+                    .to_string()
+            }
+        };
+
+        Ok(sql)
+    }
+
+    // Field values match the whole value (case-insensitively) unless they
+    // contain wildcards
+    fn like_pattern(&self) -> String {
+        if self.phrase {
+            return escape_like(&self.value);
+        }
+
+        escape_like(&self.value).replace('*', "%").replace('?', "_")
+    }
+
+    // Plain words match as prefixes, phrases and words with punctuation
+    // such as emails match as phrases. Leading wildcards aren't supported
+    // by full-text search and are dropped.
+    fn fulltext(&self) -> Option<String> {
+        let value = self.value.replace('"', "");
+        let word = value.trim_start_matches('*').trim_end_matches('*');
+        if !word.chars().any(char::is_alphanumeric) {
+            return None;
+        }
+
+        if !self.phrase && word.chars().all(char::is_alphanumeric) {
+            Some(format!("{}*", word))
+        } else {
+            Some(format!("\"{}\"", word.replace('*', "")))
+        }
+    }
+}
+
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Compact form of a parsed query, e.g. `(and a (or b c))`
+    fn show(query: &Query) -> String {
+        match query {
+            Query::Term(term) => {
+                let field = term.field.map(|field| format!("{:?}:", field)).unwrap_or_default();
+                if term.phrase {
+                    format!("{}\"{}\"", field, term.value)
+                } else {
+                    format!("{}{}", field, term.value)
+                }
+            }
+            Query::Not(query) => format!("(not {})", show(query)),
+            Query::And(parts) | Query::Or(parts) => {
+                let op = if matches!(query, Query::And(_)) { "and" } else { "or" };
+                let parts: Vec<String> = parts.iter().map(show).collect();
+                format!("({} {})", op, parts.join(" "))
+            }
+        }
+    }
+
+    fn parsed(q: &str) -> String {
+        show(&parse(q).unwrap())
+    }
+
+    fn error(q: &str) -> String {
+        match parse(q) {
+            Err(AppError::Validation(message)) => message,
+            other => panic!("expected a validation error for {:?}, got {:?}", q, other),
+        }
+    }
+
+    #[test]
+    fn tokenizes_fields_and_wildcards() {
+        assert_eq!(parsed("budi"), "budi");
+        assert_eq!(parsed("City:Bandung"), "City:Bandung");
+        assert_eq!(parsed("email:*@example.com"), "Email:*@example.com");
+        assert_eq!(parsed("postal_code:40?15"), "PostalCode:40?15");
+        assert_eq!(parsed("street:Jl.:12"), "Street:Jl.:12");
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_empty_values() {
+        assert_eq!(error("nickname:budi"), "unknown search field nickname");
+        assert_eq!(error("city:"), "search field has no value");
+        assert_eq!(error(""), "search query is empty");
+        assert_eq!(error("  ( ) "), "search query is empty");
+    }
+
+    #[test]
+    fn or_binds_tighter_than_and() {
+        assert_eq!(parsed("a b OR c"), "(and a (or b c))");
+        assert_eq!(parsed("a OR b c"), "(and (or a b) c)");
+        assert_eq!(parsed("a OR b OR c"), "(or a b c)");
+        assert_eq!(parsed("(a b) OR c"), "(or (and a b) c)");
+    }
+
+    #[test]
+    fn negation_applies_to_the_next_term_or_group() {
+        assert_eq!(parsed("-tag:inactive"), "(not Tag:inactive)");
+        assert_eq!(parsed("-a OR b"), "(or (not a) b)");
+        assert_eq!(parsed("-(a b)"), "(not (and a b))");
+        assert_eq!(parsed("--a"), "(not (not a))");
+    }
+
+    #[test]
+    fn quotes_make_phrases() {
+        assert_eq!(parsed("\"dewi s\""), "\"dewi s\"");
+        assert_eq!(parsed("city:\"New York\""), "City:\"New York\"");
+        assert_eq!(parsed("\"OR\""), "\"OR\"");
+        assert_eq!(parsed("(budi OR \"dewi s\")"), "(or budi \"dewi s\")");
+        assert_eq!(error("\"dewi s"), "search query has an unterminated quote");
+    }
+
+    #[test]
+    fn or_needs_both_sides() {
+        assert_eq!(error("OR a"), "OR needs a term on both sides");
+        assert_eq!(error("a OR"), "search query ends unexpectedly");
+        assert_eq!(error("a OR OR b"), "OR needs a term on both sides");
+    }
+
+    #[test]
+    fn parentheses_must_match() {
+        assert_eq!(error("(a b"), "search query has an unmatched (");
+        assert_eq!(error("a b)"), "search query has an unmatched )");
+        assert_eq!(error("a () b"), "search query has an empty group");
+    }
+
+    #[test]
+    fn limits_terms() {
+        let terms = |n: usize| (0..n).map(|i| format!("t{}", i)).collect::<Vec<_>>().join(" ");
+        assert!(parse(&terms(MAX_TERMS)).is_ok());
+        assert_eq!(error(&terms(MAX_TERMS + 1)), format!("search query has more than {} terms", MAX_TERMS));
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth: usize| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(parsed(&nested(MAX_DEPTH)), "a");
+        assert_eq!(error(&nested(MAX_DEPTH + 1)), "search query is nested too deeply");
+    }
+
+    #[test]
+    fn negated_terms_match_empty_columns() {
+        let (sql, params) = parse("-last_name:smith").unwrap().to_sql(country::Id::ID, "budi").unwrap();
+        assert_eq!(sql, "NOT COALESCE(last_name LIKE ?, FALSE)");
+        assert_eq!(params, ["smith"]);
+    }
+}
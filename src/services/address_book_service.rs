@@ -0,0 +1,382 @@
+use sqlx::{MySqlConnection, MySqlExecutor, MySqlPool};
+use crate::{
+    errors::{AppError, AppResult},
+    models::*,
+};
+
+const DEFAULT_BOOK_NAME: &str = "Contacts";
+
+fn parse_role(role: &str) -> AppResult<BookRole> {
+    role.parse().map_err(|e: String| {
+        tracing::error!("{}", e);
+        AppError::Internal
+    })
+}
+
+fn check_role(role: BookRole, required: BookRole) -> AppResult<()> {
+    if role < required {
+        return Err(AppError::Forbidden(format!(
+            "{} access to the address book is required",
+            required.as_str()
+        )));
+    }
+
+    Ok(())
+}
+
+// SQL condition restricting `address_book_id` to the books the user is a
+// member of with at least `required` role. Binds the username once.
+pub fn accessible_books_clause(column: &str, required: BookRole) -> String {
+    let roles: Vec<String> = [BookRole::Viewer, BookRole::Editor, BookRole::Owner]
+        .into_iter()
+        .filter(|role| *role >= required)
+        .map(|role| format!("'{}'", role.as_str()))
+        .collect();
+
+    format!(
+        "{} IN (SELECT address_book_id FROM address_book_members WHERE username = ? AND role IN ({}))",
+        column,
+        roles.join(", ")
+    )
+}
+
+pub async fn check_book_access<'e, E: MySqlExecutor<'e>>(
+    executor: E,
+    username: &str,
+    book_id: i32,
+    required: BookRole,
+) -> AppResult<()> {
+    let role: (String,) = sqlx::query_as(
+        "SELECT role FROM address_book_members WHERE address_book_id = ? AND username = ?"
+    )
+    .bind(book_id)
+    .bind(username)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(AppError::NotFound("address book is not found".to_string()))?;
+
+    check_role(parse_role(&role.0)?, required)
+}
+
+pub async fn check_contact_access<'e, E: MySqlExecutor<'e>>(
+    executor: E,
+    username: &str,
+    contact_id: i32,
+    required: BookRole,
+) -> AppResult<()> {
+    check_contact_role(executor, username, contact_id, required, false).await
+}
+
+// Same as `check_contact_access`, for contacts in the trash
+pub async fn check_deleted_contact_access<'e, E: MySqlExecutor<'e>>(
+    executor: E,
+    username: &str,
+    contact_id: i32,
+    required: BookRole,
+) -> AppResult<()> {
+    check_contact_role(executor, username, contact_id, required, true).await
+}
+
+async fn check_contact_role<'e, E: MySqlExecutor<'e>>(
+    executor: E,
+    username: &str,
+    contact_id: i32,
+    required: BookRole,
+    deleted: bool,
+) -> AppResult<()> {
+    let role: (String,) = sqlx::query_as(
+        "SELECT m.role FROM contacts c
+         JOIN address_book_members m ON m.address_book_id = c.address_book_id AND m.username = ?
+         WHERE c.id = ? AND (c.deleted_at IS NOT NULL) = ?"
+    )
+    .bind(username)
+    .bind(contact_id)
+    .bind(deleted)
+    .fetch_optional(executor)
+    .await?
+    .ok_or(AppError::NotFound("contact is not found".to_string()))?;
+
+    check_role(parse_role(&role.0)?, required)
+}
+
+// Creates a new user's default book, in the transaction that creates the
+// user so there is exactly one
+pub async fn create_default_book(conn: &mut MySqlConnection, username: &str) -> AppResult<i32> {
+    insert_book(conn, username, DEFAULT_BOOK_NAME, true).await
+}
+
+pub async fn default_book(conn: &mut MySqlConnection, username: &str) -> AppResult<i32> {
+    let (id,): (i32,) = sqlx::query_as(
+        "SELECT id FROM address_books WHERE username = ? AND is_default = TRUE"
+    )
+    .bind(username)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| {
+        tracing::error!("user {} has no default address book", username);
+        AppError::Internal
+    })?;
+
+    Ok(id)
+}
+
+async fn insert_book(
+    conn: &mut MySqlConnection,
+    username: &str,
+    name: &str,
+    is_default: bool,
+) -> AppResult<i32> {
+    let result = sqlx::query(
+        "INSERT INTO address_books (name, username, is_default) VALUES (?, ?, ?)"
+    )
+    .bind(name)
+    .bind(username)
+    .bind(is_default)
+    .execute(&mut *conn)
+    .await?;
+
+    let id = result.last_insert_id() as i32;
+
+    sqlx::query(
+        "INSERT INTO address_book_members (address_book_id, username, role) VALUES (?, ?, ?)"
+    )
+    .bind(id)
+    .bind(username)
+    .bind(BookRole::Owner.as_str())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn create(
+    pool: &MySqlPool,
+    username: &str,
+    req: CreateAddressBookRequest,
+) -> AppResult<AddressBookResponse> {
+    let mut tx = pool.begin().await?;
+    let id = insert_book(&mut tx, username, &req.name, false).await?;
+    tx.commit().await?;
+
+    Ok(AddressBookResponse {
+        id,
+        name: req.name,
+        is_default: false,
+        role: BookRole::Owner,
+    })
+}
+
+pub async fn list(pool: &MySqlPool, username: &str) -> AppResult<Vec<AddressBookResponse>> {
+    let books: Vec<(i32, String, bool, String)> = sqlx::query_as(
+        "SELECT b.id, b.name, b.is_default AND b.username = m.username, m.role
+         FROM address_books b
+         JOIN address_book_members m ON m.address_book_id = b.id
+         WHERE m.username = ?
+         ORDER BY b.name, b.id"
+    )
+    .bind(username)
+    .fetch_all(pool)
+    .await?;
+
+    books
+        .into_iter()
+        .map(|(id, name, is_default, role)| {
+            Ok(AddressBookResponse { id, name, is_default, role: parse_role(&role)? })
+        })
+        .collect()
+}
+
+pub async fn get(
+    pool: &MySqlPool,
+    username: &str,
+    book_id: i32,
+) -> AppResult<AddressBookResponse> {
+    let (id, name, is_default, role): (i32, String, bool, String) = sqlx::query_as(
+        "SELECT b.id, b.name, b.is_default AND b.username = m.username, m.role
+         FROM address_books b
+         JOIN address_book_members m ON m.address_book_id = b.id
+         WHERE b.id = ? AND m.username = ?"
+    )
+    .bind(book_id)
+    .bind(username)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AppError::NotFound("address book is not found".to_string()))?;
+
+    Ok(AddressBookResponse { id, name, is_default, role: parse_role(&role)? })
+}
+
+pub async fn update(
+    pool: &MySqlPool,
+    username: &str,
+    book_id: i32,
+    req: UpdateAddressBookRequest,
+) -> AppResult<AddressBookResponse> {
+    check_book_access(pool, username, book_id, BookRole::Owner).await?;
+
+    sqlx::query("UPDATE address_books SET name = ? WHERE id = ?")
+        .bind(&req.name)
+        .bind(book_id)
+        .execute(pool)
+        .await?;
+
+    get(pool, username, book_id).await
+}
+
+pub async fn remove(pool: &MySqlPool, username: &str, book_id: i32) -> AppResult<()> {
+    check_book_access(pool, username, book_id, BookRole::Owner).await?;
+
+    let book = sqlx::query_as::<_, AddressBook>(
+        "SELECT id, name, username, is_default FROM address_books WHERE id = ?"
+    )
+    .bind(book_id)
+    .fetch_one(pool)
+    .await?;
+
+    if book.is_default {
+        return Err(AppError::BadRequest("default address book cannot be deleted".to_string()));
+    }
+
+    let count: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM contacts WHERE address_book_id = ? AND deleted_at IS NULL"
+    )
+    .bind(book_id)
+    .fetch_one(pool)
+    .await?;
+
+    if count.0 > 0 {
+        return Err(AppError::BadRequest("address book is not empty".to_string()));
+    }
+
+    // Contacts still in the trash go with the book
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "DELETE a FROM addresses a JOIN contacts c ON c.id = a.contact_id WHERE c.address_book_id = ?"
+    )
+    .bind(book_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM contacts WHERE address_book_id = ?")
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM address_books WHERE id = ?")
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn list_members(
+    pool: &MySqlPool,
+    username: &str,
+    book_id: i32,
+) -> AppResult<Vec<AddressBookMemberResponse>> {
+    check_book_access(pool, username, book_id, BookRole::Viewer).await?;
+
+    let members: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT m.username, u.name, m.role
+         FROM address_book_members m
+         JOIN users u ON u.username = m.username
+         WHERE m.address_book_id = ?
+         ORDER BY m.username"
+    )
+    .bind(book_id)
+    .fetch_all(pool)
+    .await?;
+
+    members
+        .into_iter()
+        .map(|(username, name, role)| {
+            Ok(AddressBookMemberResponse { username, name, role: parse_role(&role)? })
+        })
+        .collect()
+}
+
+pub async fn set_member(
+    pool: &MySqlPool,
+    username: &str,
+    book_id: i32,
+    member: &str,
+    req: UpdateMemberRequest,
+) -> AppResult<Vec<AddressBookMemberResponse>> {
+    check_book_access(pool, username, book_id, BookRole::Owner).await?;
+
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE username = ?")
+        .bind(member)
+        .fetch_one(pool)
+        .await?;
+
+    if count.0 != 1 {
+        return Err(AppError::NotFound("user is not found".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO address_book_members (address_book_id, username, role) VALUES (?, ?, ?)
+         ON DUPLICATE KEY UPDATE role = VALUES(role)"
+    )
+    .bind(book_id)
+    .bind(member)
+    .bind(req.role.as_str())
+    .execute(&mut *tx)
+    .await?;
+
+    check_has_owner(&mut tx, book_id).await?;
+    tx.commit().await?;
+
+    list_members(pool, username, book_id).await
+}
+
+// Members may always leave a book, removing anyone else requires ownership.
+pub async fn remove_member(
+    pool: &MySqlPool,
+    username: &str,
+    book_id: i32,
+    member: &str,
+) -> AppResult<()> {
+    let required = if member == username { BookRole::Viewer } else { BookRole::Owner };
+    check_book_access(pool, username, book_id, required).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        "DELETE FROM address_book_members WHERE address_book_id = ? AND username = ?"
+    )
+    .bind(book_id)
+    .bind(member)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("member is not found".to_string()));
+    }
+
+    check_has_owner(&mut tx, book_id).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn check_has_owner(conn: &mut MySqlConnection, book_id: i32) -> AppResult<()> {
+    let owners: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM address_book_members WHERE address_book_id = ? AND role = ?"
+    )
+    .bind(book_id)
+    .bind(BookRole::Owner.as_str())
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if owners.0 == 0 {
+        return Err(AppError::BadRequest("address book must keep at least one owner".to_string()));
+    }
+
+    Ok(())
+}
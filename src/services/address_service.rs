@@ -1,129 +1,361 @@
-use sqlx::MySqlPool;
+use std::sync::Arc;
+use sqlx::{FromRow, MySqlConnection, MySqlPool, Row};
 use crate::{
+    address_format,
+    countries,
     errors::{AppError, AppResult},
+    etag::IfMatch,
+    geocoder::{GeocodeQuery, Geocoder},
+    merge_patch,
     models::*,
+    services::{
+        address_book_service,
+        audit_service::{self, AuditTarget},
+        webhook_service,
+    },
+    validation::validate_patched,
 };
 
+const EARTH_RADIUS_KM: f64 = 6371.0;
+const NEARBY_LIMIT: i32 = 100;
+
+// Access to a contact's addresses follows the role in its address book
 async fn check_contact_exists(
     pool: &MySqlPool,
     username: &str,
     contact_id: i32,
+    required: BookRole,
 ) -> AppResult<()> {
-    let count: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM contacts WHERE id = ? AND username = ?"
-    )
-    .bind(contact_id)
-    .bind(username)
-    .fetch_one(pool)
-    .await?;
-
-    if count.0 != 1 {
-        return Err(AppError::NotFound("contact is not found".to_string()));
-    }
-
-    Ok(())
+    address_book_service::check_contact_access(pool, username, contact_id, required).await
 }
 
+pub const ADDRESS_COLUMNS: &str =
+    "id, street, city, province, country, postal_code, latitude, longitude, contact_id, address_type, is_primary, \
+     version, created_at, updated_at";
+
 pub async fn create(
     pool: &MySqlPool,
+    geocoder: &Arc<dyn Geocoder>,
     username: &str,
     contact_id: i32,
     req: CreateAddressRequest,
 ) -> AppResult<AddressResponse> {
-    check_contact_exists(pool, username, contact_id).await?;
+    check_contact_exists(pool, username, contact_id, BookRole::Editor).await?;
+
+    let mut tx = pool.begin().await?;
+    let address = create_in(&mut tx, username, contact_id, &req).await?;
+    tx.commit().await?;
+
+    geocode_later(pool, geocoder, address.id);
+
+    Ok(address)
+}
+
+// `create` within an open transaction, for a contact the caller can edit.
+// The caller geocodes the address once the transaction is committed.
+pub async fn create_in(
+    conn: &mut MySqlConnection,
+    username: &str,
+    contact_id: i32,
+    req: &CreateAddressRequest,
+) -> AppResult<AddressResponse> {
+    let id = insert(conn, contact_id, req).await?;
+    touch_contact(conn, contact_id).await?;
+
+    let address = find_address(conn, contact_id, id).await?;
+    audit_service::record(
+        conn,
+        username,
+        AuditAction::Create,
+        AuditTarget::Address { contact_id, id },
+        None,
+        Some(audit_service::snapshot(&address)),
+    )
+    .await?;
+    webhook_service::enqueue(conn, WebhookEvent::AddressCreated, contact_id, &address).await?;
+
+    Ok(address)
+}
+
+// Looks up the address's coordinates in the background so saving an
+// address never waits on the geocoder
+pub fn geocode_later(pool: &MySqlPool, geocoder: &Arc<dyn Geocoder>, address_id: i32) {
+    let pool = pool.clone();
+    let geocoder = geocoder.clone();
+    tokio::spawn(async move {
+        if let Err(e) = geocode(&pool, geocoder.as_ref(), address_id).await {
+            tracing::warn!("Could not geocode address {}: {}", address_id, e);
+        }
+    });
+}
+
+async fn geocode(pool: &MySqlPool, geocoder: &dyn Geocoder, address_id: i32) -> AppResult<()> {
+    let Some(address) = sqlx::query_as::<_, Address>(&format!(
+        "SELECT {} FROM addresses WHERE id = ?",
+        ADDRESS_COLUMNS
+    ))
+    .bind(address_id)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let query = GeocodeQuery {
+        country: &address.country,
+        postal_code: &address.postal_code,
+    };
+    let Some(coordinates) = geocoder.geocode(&query).await? else {
+        return Ok(());
+    };
+
+    // Skipped when the location changed while the lookup ran; the edit
+    // starts a lookup of its own
+    sqlx::query(
+        "UPDATE addresses SET latitude = ?, longitude = ?
+         WHERE id = ? AND country = ? AND postal_code = ?"
+    )
+    .bind(coordinates.latitude)
+    .bind(coordinates.longitude)
+    .bind(address_id)
+    .bind(&address.country)
+    .bind(&address.postal_code)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// Inserts an address, taking the primary flag from the contact's other
+// addresses when this one is primary.
+async fn insert(
+    conn: &mut MySqlConnection,
+    contact_id: i32,
+    req: &CreateAddressRequest,
+) -> AppResult<i32> {
+    if req.is_primary {
+        clear_primary(conn, contact_id).await?;
+    }
 
     let result = sqlx::query(
-        "INSERT INTO addresses (street, city, province, country, postal_code, contact_id) 
-         VALUES (?, ?, ?, ?, ?, ?)"
+        "INSERT INTO addresses (street, city, province, country, postal_code, contact_id, address_type, is_primary) 
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&req.street)
     .bind(&req.city)
     .bind(&req.province)
-    .bind(&req.country)
+    .bind(country_code(&req.country))
     .bind(&req.postal_code)
     .bind(contact_id)
-    .execute(pool)
+    .bind(req.address_type.as_deref().unwrap_or("other"))
+    .bind(req.is_primary)
+    .execute(&mut *conn)
     .await?;
 
-    let id = result.last_insert_id() as i32;
+    Ok(result.last_insert_id() as i32)
+}
 
-    Ok(AddressResponse {
-        id,
-        street: req.street,
-        city: req.city,
-        province: req.province,
-        country: req.country,
-        postal_code: req.postal_code,
-    })
+// Requests are validated, so unknown countries only come from old data
+fn country_code(country: &str) -> &str {
+    countries::find(country).map_or(country, |c| c.code)
 }
 
-pub async fn get(
-    pool: &MySqlPool,
-    username: &str,
+// Locks the contact first so concurrent requests can't both leave a
+// primary address behind
+async fn clear_primary(conn: &mut MySqlConnection, contact_id: i32) -> AppResult<()> {
+    sqlx::query("SELECT id FROM contacts WHERE id = ? FOR UPDATE")
+        .bind(contact_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        "UPDATE addresses SET is_primary = FALSE, version = version + 1, updated_at = CURRENT_TIMESTAMP 
+         WHERE contact_id = ? AND is_primary = TRUE"
+    )
+    .bind(contact_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// Address changes count as changes to the contact, whose responses
+// include its primary address
+async fn touch_contact(conn: &mut MySqlConnection, contact_id: i32) -> AppResult<()> {
+    sqlx::query("UPDATE contacts SET version = version + 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(contact_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+// Locks the address for the rest of the transaction and compares its
+// version with the caller's If-Match
+async fn check_version(
+    conn: &mut MySqlConnection,
     contact_id: i32,
     address_id: i32,
-) -> AppResult<AddressResponse> {
-    check_contact_exists(pool, username, contact_id).await?;
+    if_match: &IfMatch,
+) -> AppResult<()> {
+    let (version,): (i32,) = sqlx::query_as(
+        "SELECT version FROM addresses WHERE id = ? AND contact_id = ? AND deleted_at IS NULL FOR UPDATE"
+    )
+    .bind(address_id)
+    .bind(contact_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound("address is not found".to_string()))?;
 
-    let address = sqlx::query_as::<_, Address>(
-        "SELECT id, street, city, province, country, postal_code, contact_id 
+    if_match.check(version)
+}
+
+async fn find_address(
+    conn: &mut MySqlConnection,
+    contact_id: i32,
+    address_id: i32,
+) -> AppResult<AddressResponse> {
+    let address = sqlx::query_as::<_, Address>(&format!(
+        "SELECT {} 
          FROM addresses 
-         WHERE id = ? AND contact_id = ?"
-    )
+         WHERE id = ? AND contact_id = ? AND deleted_at IS NULL",
+        ADDRESS_COLUMNS
+    ))
     .bind(address_id)
     .bind(contact_id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or(AppError::NotFound("address is not found".to_string()))?;
 
     Ok(address.into())
 }
 
+pub async fn get(
+    pool: &MySqlPool,
+    username: &str,
+    contact_id: i32,
+    address_id: i32,
+    format: AddressFormat,
+) -> AppResult<AddressResponse> {
+    check_contact_exists(pool, username, contact_id, BookRole::Viewer).await?;
+
+    let mut address = find_address(&mut *pool.acquire().await?, contact_id, address_id).await?;
+    if format != AddressFormat::Label {
+        address.formatted = address_format::format(&address, format);
+    }
+
+    Ok(address)
+}
+
 pub async fn update(
     pool: &MySqlPool,
+    geocoder: &Arc<dyn Geocoder>,
     username: &str,
     contact_id: i32,
     address_id: i32,
     req: UpdateAddressRequest,
+    if_match: &IfMatch,
 ) -> AppResult<AddressResponse> {
-    check_contact_exists(pool, username, contact_id).await?;
+    check_contact_exists(pool, username, contact_id, BookRole::Editor).await?;
 
-    // Check if address exists
-    let count: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM addresses WHERE id = ? AND contact_id = ?"
-    )
-    .bind(address_id)
-    .bind(contact_id)
-    .fetch_one(pool)
-    .await?;
+    let mut tx = pool.begin().await?;
+    check_version(&mut tx, contact_id, address_id, if_match).await?;
+    let address = save_update(&mut tx, username, contact_id, address_id, req).await?;
+    tx.commit().await?;
 
-    if count.0 != 1 {
-        return Err(AppError::NotFound("address is not found".to_string()));
+    geocode_later(pool, geocoder, address_id);
+
+    Ok(address)
+}
+
+// Applies a JSON merge patch to the address as an update request would
+// describe it
+pub async fn patch(
+    pool: &MySqlPool,
+    geocoder: &Arc<dyn Geocoder>,
+    username: &str,
+    contact_id: i32,
+    address_id: i32,
+    patch: serde_json::Value,
+    if_match: &IfMatch,
+) -> AppResult<AddressResponse> {
+    let mut tx = pool.begin().await?;
+    address_book_service::check_contact_access(&mut *tx, username, contact_id, BookRole::Editor).await?;
+    check_version(&mut tx, contact_id, address_id, if_match).await?;
+
+    let current = find_address(&mut tx, contact_id, address_id).await?;
+    let document = serde_json::json!({
+        "street": current.street,
+        "city": current.city,
+        "province": current.province,
+        "country": current.country,
+        "postal_code": current.postal_code,
+        "type": current.address_type,
+        "is_primary": current.is_primary,
+    });
+    let req: UpdateAddressRequest = merge_patch::merge(document, &patch)?;
+    validate_patched(&req, |field| match field {
+        "address_type" => patch.get("type").is_some(),
+        // The postal code is checked against the country
+        "__all__" => patch.get("country").is_some() || patch.get("postal_code").is_some(),
+        _ => patch.get(field).is_some(),
+    })?;
+
+    let address = save_update(&mut tx, username, contact_id, address_id, req).await?;
+    tx.commit().await?;
+
+    geocode_later(pool, geocoder, address_id);
+
+    Ok(address)
+}
+
+// Updates a locked address and records the audit entry
+async fn save_update(
+    conn: &mut MySqlConnection,
+    username: &str,
+    contact_id: i32,
+    address_id: i32,
+    req: UpdateAddressRequest,
+) -> AppResult<AddressResponse> {
+    let before = find_address(conn, contact_id, address_id).await?;
+
+    if req.is_primary {
+        clear_primary(conn, contact_id).await?;
     }
 
-    // Update address
+    // Update address; coordinates are looked up again after the commit
     sqlx::query(
         "UPDATE addresses 
-         SET street = ?, city = ?, province = ?, country = ?, postal_code = ? 
+         SET street = ?, city = ?, province = ?, country = ?, postal_code = ?, address_type = ?, is_primary = ?, 
+             latitude = NULL, longitude = NULL, version = version + 1, updated_at = CURRENT_TIMESTAMP 
          WHERE id = ?"
     )
     .bind(&req.street)
     .bind(&req.city)
     .bind(&req.province)
-    .bind(&req.country)
+    .bind(country_code(&req.country))
     .bind(&req.postal_code)
+    .bind(req.address_type.as_deref().unwrap_or("other"))
+    .bind(req.is_primary)
     .bind(address_id)
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
-    Ok(AddressResponse {
-        id: address_id,
-        street: req.street,
-        city: req.city,
-        province: req.province,
-        country: req.country,
-        postal_code: req.postal_code,
-    })
+    touch_contact(conn, contact_id).await?;
+
+    let address = find_address(conn, contact_id, address_id).await?;
+    audit_service::record(
+        conn,
+        username,
+        AuditAction::Update,
+        AuditTarget::Address { contact_id, id: address_id },
+        Some(audit_service::snapshot(&before)),
+        Some(audit_service::snapshot(&address)),
+    )
+    .await?;
+    webhook_service::enqueue(conn, WebhookEvent::AddressUpdated, contact_id, &address).await?;
+
+    Ok(address)
 }
 
 pub async fn remove(
@@ -131,39 +363,475 @@ pub async fn remove(
     username: &str,
     contact_id: i32,
     address_id: i32,
+    if_match: &IfMatch,
 ) -> AppResult<()> {
-    check_contact_exists(pool, username, contact_id).await?;
+    check_contact_exists(pool, username, contact_id, BookRole::Editor).await?;
+
+    let mut tx = pool.begin().await?;
+    check_version(&mut tx, contact_id, address_id, if_match).await?;
+    let before = find_address(&mut tx, contact_id, address_id).await?;
+
+    // Moved to the trash; a restored address is no longer primary
+    sqlx::query(
+        "UPDATE addresses SET deleted_at = CURRENT_TIMESTAMP, is_primary = FALSE, version = version + 1, updated_at = CURRENT_TIMESTAMP 
+         WHERE id = ?"
+    )
+    .bind(address_id)
+    .execute(&mut *tx)
+    .await?;
+
+    touch_contact(&mut tx, contact_id).await?;
+
+    audit_service::record(
+        &mut tx,
+        username,
+        AuditAction::Delete,
+        AuditTarget::Address { contact_id, id: address_id },
+        Some(audit_service::snapshot(&before)),
+        None,
+    )
+    .await?;
+    webhook_service::enqueue(&mut tx, WebhookEvent::AddressDeleted, contact_id, &before).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn restore(
+    pool: &MySqlPool,
+    username: &str,
+    contact_id: i32,
+    address_id: i32,
+) -> AppResult<AddressResponse> {
+    check_contact_exists(pool, username, contact_id, BookRole::Editor).await?;
+
+    let mut tx = pool.begin().await?;
 
     let result = sqlx::query(
-        "DELETE FROM addresses WHERE id = ? AND contact_id = ?"
+        "UPDATE addresses SET deleted_at = NULL, version = version + 1, updated_at = CURRENT_TIMESTAMP 
+         WHERE id = ? AND contact_id = ? AND deleted_at IS NOT NULL"
     )
     .bind(address_id)
     .bind(contact_id)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
     if result.rows_affected() == 0 {
         return Err(AppError::NotFound("address is not found".to_string()));
     }
 
+    touch_contact(&mut tx, contact_id).await?;
+
+    let address = find_address(&mut tx, contact_id, address_id).await?;
+    audit_service::record(
+        &mut tx,
+        username,
+        AuditAction::Restore,
+        AuditTarget::Address { contact_id, id: address_id },
+        None,
+        Some(audit_service::snapshot(&address)),
+    )
+    .await?;
+    webhook_service::enqueue(&mut tx, WebhookEvent::AddressRestored, contact_id, &address).await?;
+
+    tx.commit().await?;
+
+    Ok(address)
+}
+
+// Makes the contact's live addresses match `addresses`: listed ones are
+// updated (or restored from the trash, or added again once purged) and the
+// rest go to the trash, each with its own audit entry and webhook event.
+// Addresses that already match are left alone. The contact must be locked.
+// Returns the ids of the addresses that changed, to be geocoded.
+pub async fn replace_all(
+    conn: &mut MySqlConnection,
+    username: &str,
+    contact_id: i32,
+    addresses: &[AddressSnapshot],
+) -> AppResult<Vec<i32>> {
+    let mut changed = Vec::new();
+    let mut purged = Vec::new();
+    for snapshot in addresses {
+        let req = &snapshot.address;
+        let (count, trashed): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COUNT(deleted_at) FROM addresses WHERE id = ? AND contact_id = ?"
+        )
+        .bind(snapshot.id)
+        .bind(contact_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if count == 0 {
+            purged.push(req);
+            continue;
+        }
+
+        let before = if trashed == 0 {
+            Some(find_address(conn, contact_id, snapshot.id).await?)
+        } else {
+            None
+        };
+        if before.as_ref().is_some_and(|before| is_same(before, req)) {
+            continue;
+        }
+
+        sqlx::query(
+            "UPDATE addresses
+             SET street = ?, city = ?, province = ?, country = ?, postal_code = ?, address_type = ?, is_primary = ?,
+                 latitude = NULL, longitude = NULL, deleted_at = NULL, version = version + 1, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?"
+        )
+        .bind(&req.street)
+        .bind(&req.city)
+        .bind(&req.province)
+        .bind(country_code(&req.country))
+        .bind(&req.postal_code)
+        .bind(req.address_type.as_deref().unwrap_or("other"))
+        .bind(req.is_primary)
+        .bind(snapshot.id)
+        .execute(&mut *conn)
+        .await?;
+
+        let address = find_address(conn, contact_id, snapshot.id).await?;
+        let (action, event) = match before {
+            Some(_) => (AuditAction::Update, WebhookEvent::AddressUpdated),
+            None => (AuditAction::Restore, WebhookEvent::AddressRestored),
+        };
+        audit_service::record(
+            conn,
+            username,
+            action,
+            AuditTarget::Address { contact_id, id: snapshot.id },
+            before.map(|before| audit_service::snapshot(&before)),
+            Some(audit_service::snapshot(&address)),
+        )
+        .await?;
+        webhook_service::enqueue(conn, event, contact_id, &address).await?;
+        changed.push(snapshot.id);
+    }
+
+    let live: Vec<(i32,)> = sqlx::query_as("SELECT id FROM addresses WHERE contact_id = ? AND deleted_at IS NULL")
+        .bind(contact_id)
+        .fetch_all(&mut *conn)
+        .await?;
+    for (id,) in live {
+        if addresses.iter().any(|snapshot| snapshot.id == id) {
+            continue;
+        }
+
+        let before = find_address(conn, contact_id, id).await?;
+        sqlx::query(
+            "UPDATE addresses SET deleted_at = CURRENT_TIMESTAMP, is_primary = FALSE, version = version + 1, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?"
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        audit_service::record(
+            conn,
+            username,
+            AuditAction::Delete,
+            AuditTarget::Address { contact_id, id },
+            Some(audit_service::snapshot(&before)),
+            None,
+        )
+        .await?;
+        webhook_service::enqueue(conn, WebhookEvent::AddressDeleted, contact_id, &before).await?;
+    }
+
+    // Added last: every other address already has its final primary flag
+    for req in purged {
+        let id = insert(conn, contact_id, req).await?;
+        let address = find_address(conn, contact_id, id).await?;
+        audit_service::record(
+            conn,
+            username,
+            AuditAction::Create,
+            AuditTarget::Address { contact_id, id },
+            None,
+            Some(audit_service::snapshot(&address)),
+        )
+        .await?;
+        webhook_service::enqueue(conn, WebhookEvent::AddressCreated, contact_id, &address).await?;
+        changed.push(id);
+    }
+
+    Ok(changed)
+}
+
+// Moves the live addresses of a contact merged into `target_id` over to
+// it, keeping their ids. They stay primary only when `keep_primary` is
+// set. Both contacts must be locked.
+pub async fn move_all(
+    conn: &mut MySqlConnection,
+    username: &str,
+    source_id: i32,
+    target_id: i32,
+    keep_primary: bool,
+) -> AppResult<()> {
+    let ids: Vec<(i32,)> = sqlx::query_as(
+        "SELECT id FROM addresses WHERE contact_id = ? AND deleted_at IS NULL ORDER BY id"
+    )
+    .bind(source_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for (id,) in ids {
+        sqlx::query(
+            "UPDATE addresses 
+             SET contact_id = ?, is_primary = is_primary AND ?, version = version + 1, updated_at = CURRENT_TIMESTAMP 
+             WHERE id = ?"
+        )
+        .bind(target_id)
+        .bind(keep_primary)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+        let address = find_address(conn, target_id, id).await?;
+        audit_service::record(
+            conn,
+            username,
+            AuditAction::Merge,
+            AuditTarget::Address { contact_id: target_id, id },
+            None,
+            Some(audit_service::snapshot(&address)),
+        )
+        .await?;
+        webhook_service::enqueue(conn, WebhookEvent::AddressCreated, target_id, &address).await?;
+    }
+
     Ok(())
 }
 
+// Whether a live address already reads as `req`
+fn is_same(address: &AddressResponse, req: &CreateAddressRequest) -> bool {
+    address.street == req.street
+        && address.city == req.city
+        && address.province == req.province
+        && address.country == country_code(&req.country)
+        && address.postal_code == req.postal_code
+        && address.address_type == req.address_type.as_deref().unwrap_or("other")
+        && address.is_primary == req.is_primary
+}
+
 pub async fn list(
     pool: &MySqlPool,
     username: &str,
     contact_id: i32,
 ) -> AppResult<Vec<AddressResponse>> {
-    check_contact_exists(pool, username, contact_id).await?;
+    check_contact_exists(pool, username, contact_id, BookRole::Viewer).await?;
 
-    let addresses = sqlx::query_as::<_, Address>(
-        "SELECT id, street, city, province, country, postal_code, contact_id 
+    let addresses = sqlx::query_as::<_, Address>(&format!(
+        "SELECT {} 
          FROM addresses 
-         WHERE contact_id = ?"
-    )
+         WHERE contact_id = ? AND deleted_at IS NULL 
+         ORDER BY is_primary DESC, id",
+        ADDRESS_COLUMNS
+    ))
     .bind(contact_id)
     .fetch_all(pool)
     .await?;
 
     Ok(addresses.into_iter().map(|a| a.into()).collect())
-}
\ No newline at end of file
+}
+
+// Searches the addresses of every contact the caller can see, ordered by
+// location so nearby addresses end up next to each other.
+pub async fn search(
+    pool: &MySqlPool,
+    username: &str,
+    req: SearchAddressRequest,
+) -> AppResult<AddressSearchResponse> {
+    let page = req.page.unwrap_or(1).max(1);
+    let size = req.size.unwrap_or(10).clamp(1, 100);
+    let offset = (page - 1) * size;
+
+    let mut where_clauses = vec![
+        "a.deleted_at IS NULL AND c.deleted_at IS NULL".to_string(),
+        address_book_service::accessible_books_clause("c.address_book_id", BookRole::Viewer),
+    ];
+    let mut query_params: Vec<String> = vec![username.to_string()];
+
+    for (column, value) in [("a.city", &req.city), ("a.province", &req.province)] {
+        if let Some(value) = value {
+            where_clauses.push(format!("{} LIKE ?", column));
+            query_params.push(format!("%{}%", value));
+        }
+    }
+
+    // Known countries match on their code, anything else on the stored text
+    if let Some(country) = &req.country {
+        match countries::find(country) {
+            Some(country) => {
+                where_clauses.push("a.country = ?".to_string());
+                query_params.push(country.code.to_string());
+            }
+            None => {
+                where_clauses.push("a.country LIKE ?".to_string());
+                query_params.push(format!("%{}%", country));
+            }
+        }
+    }
+
+    if let Some(postal_code) = &req.postal_code {
+        where_clauses.push("a.postal_code LIKE ?".to_string());
+        query_params.push(format!("{}%", postal_code));
+    }
+
+    let where_clause = where_clauses.join(" AND ");
+
+    let count_query = format!(
+        "SELECT COUNT(*) FROM addresses a JOIN contacts c ON c.id = a.contact_id WHERE {}",
+        where_clause
+    );
+    let mut count_query_builder = sqlx::query_as::<_, (i64,)>(&count_query);
+    for param in &query_params {
+        count_query_builder = count_query_builder.bind(param);
+    }
+    let total_item = count_query_builder.fetch_one(pool).await?.0;
+
+    let select_query = format!(
+        "SELECT {}, c.first_name, c.last_name 
+         FROM addresses a 
+         JOIN contacts c ON c.id = a.contact_id 
+         WHERE {} 
+         ORDER BY a.country, a.province, a.city, a.postal_code, a.id 
+         LIMIT ? OFFSET ?",
+        joined_address_columns(),
+        where_clause
+    );
+    let mut query_builder = sqlx::query(&select_query);
+    for param in &query_params {
+        query_builder = query_builder.bind(param);
+    }
+    query_builder = query_builder.bind(size).bind(offset);
+
+    let mut data = Vec::new();
+    for row in query_builder.fetch_all(pool).await? {
+        data.push(contact_address(&row)?);
+    }
+
+    let total_page = ((total_item as f64) / (size as f64)).ceil() as i32;
+
+    Ok(AddressSearchResponse {
+        data,
+        paging: PagingResponse {
+            page: Some(page),
+            total_page,
+            total_item,
+        },
+    })
+}
+
+pub fn joined_address_columns() -> String {
+    ADDRESS_COLUMNS.split(", ").map(|c| format!("a.{}", c)).collect::<Vec<_>>().join(", ")
+}
+
+pub fn contact_address(row: &sqlx::mysql::MySqlRow) -> AppResult<ContactAddressResponse> {
+    let address = Address::from_row(row)?;
+    Ok(ContactAddressResponse {
+        contact: AddressContactResponse {
+            id: address.contact_id,
+            first_name: row.try_get("first_name")?,
+            last_name: row.try_get("last_name")?,
+        },
+        address: address.into(),
+    })
+}
+
+// Finds geocoded addresses of visible contacts within `radius_km` of a
+// point, nearest first. A bounding box on the indexed coordinates narrows
+// the rows before the great-circle distance is computed.
+pub async fn nearby(
+    pool: &MySqlPool,
+    username: &str,
+    req: NearbyAddressRequest,
+) -> AppResult<Vec<NearbyAddressResponse>> {
+    let radius = req.radius_km.unwrap_or(10.0);
+
+    let lat_delta = (radius / EARTH_RADIUS_KM).to_degrees();
+    let lng_delta = match req.lat.to_radians().cos() {
+        // Near the poles and across the antimeridian every longitude is in range
+        cos if cos > 0.01 => (lat_delta / cos).min(180.0),
+        _ => 180.0,
+    };
+    let (min_lng, max_lng) = if req.lng - lng_delta < -180.0 || req.lng + lng_delta > 180.0 {
+        (-180.0, 180.0)
+    } else {
+        (req.lng - lng_delta, req.lng + lng_delta)
+    };
+
+    let query = format!(
+        "SELECT {}, c.first_name, c.last_name, 
+                {} * ACOS(LEAST(1, COS(RADIANS(?)) * COS(RADIANS(a.latitude)) 
+                    * COS(RADIANS(a.longitude) - RADIANS(?)) 
+                    + SIN(RADIANS(?)) * SIN(RADIANS(a.latitude)))) AS distance_km 
+         FROM addresses a 
+         JOIN contacts c ON c.id = a.contact_id 
+         WHERE a.deleted_at IS NULL AND c.deleted_at IS NULL AND {} 
+           AND a.latitude BETWEEN ? AND ? 
+           AND a.longitude BETWEEN ? AND ? 
+         HAVING distance_km <= ? 
+         ORDER BY distance_km, a.id 
+         LIMIT ?",
+        joined_address_columns(),
+        EARTH_RADIUS_KM,
+        address_book_service::accessible_books_clause("c.address_book_id", BookRole::Viewer)
+    );
+
+    let rows = sqlx::query(&query)
+        .bind(req.lat)
+        .bind(req.lng)
+        .bind(req.lat)
+        .bind(username)
+        .bind(req.lat - lat_delta)
+        .bind(req.lat + lat_delta)
+        .bind(min_lng)
+        .bind(max_lng)
+        .bind(radius)
+        .bind(NEARBY_LIMIT)
+        .fetch_all(pool)
+        .await?;
+
+    let mut data = Vec::new();
+    for row in rows {
+        data.push(NearbyAddressResponse {
+            distance_km: row.try_get("distance_km")?,
+            address: contact_address(&row)?,
+        });
+    }
+
+    Ok(data)
+}
+
+// Replaces country names stored before countries were validated with their
+// ISO code. Values that aren't recognized stay as they are.
+pub async fn normalize_countries(pool: &MySqlPool) -> AppResult<u64> {
+    let mut normalized = 0;
+
+    // Binary collation so "indonesia" and "Indonesia" are both listed
+    let stored: Vec<(String,)> =
+        sqlx::query_as("SELECT DISTINCT country COLLATE utf8mb4_bin FROM addresses")
+        .fetch_all(pool)
+        .await?;
+    for (country,) in stored {
+        match countries::find(&country) {
+            Some(found) if found.code != country => {
+                let result = sqlx::query("UPDATE addresses SET country = ? WHERE country = ?")
+                    .bind(found.code)
+                    .bind(&country)
+                    .execute(pool)
+                    .await?;
+                normalized += result.rows_affected();
+            }
+            Some(_) => {}
+            None => tracing::warn!("Address country {} is not a known country", country),
+        }
+    }
+
+    Ok(normalized)
+}
@@ -0,0 +1,213 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::{MySqlConnection, MySqlPool};
+use crate::{
+    errors::{AppError, AppResult},
+    middleware::current_request_id,
+    models::*,
+    services::address_book_service,
+};
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+// Fields that change on every write and would only add noise
+const IGNORED_FIELDS: [&str; 2] = ["updated_at", "version"];
+
+// Per-user contact fields. History is shared with everyone who can view the
+// contact, so these are never stored.
+const PRIVATE_FIELDS: [&str; 2] = ["tags", "custom_fields"];
+
+// What an audit entry is about
+pub enum AuditTarget<'a> {
+    Contact(i32),
+    Address { contact_id: i32, id: i32 },
+    User(&'a str),
+}
+
+impl AuditTarget<'_> {
+    fn entity(&self) -> &'static str {
+        match self {
+            AuditTarget::Contact(_) => "contact",
+            AuditTarget::Address { .. } => "address",
+            AuditTarget::User(_) => "user",
+        }
+    }
+
+    fn entity_id(&self) -> String {
+        match self {
+            AuditTarget::Contact(id) | AuditTarget::Address { id, .. } => id.to_string(),
+            AuditTarget::User(username) => username.to_string(),
+        }
+    }
+
+    fn contact_id(&self) -> Option<i32> {
+        match self {
+            AuditTarget::Contact(id) | AuditTarget::Address { contact_id: id, .. } => Some(*id),
+            AuditTarget::User(_) => None,
+        }
+    }
+}
+
+pub fn snapshot<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+// Writes an audit entry, meant to run in the transaction of the change it
+// records. `before` is None for creates and `after` for deletes.
+pub async fn record(
+    conn: &mut MySqlConnection,
+    actor: &str,
+    action: AuditAction,
+    target: AuditTarget<'_>,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> AppResult<()> {
+    sqlx::query(
+        "INSERT INTO audit_log (actor, entity, entity_id, contact_id, action, changes, request_id)
+         VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(actor)
+    .bind(target.entity())
+    .bind(target.entity_id())
+    .bind(target.contact_id())
+    .bind(action.as_str())
+    .bind(changes(before, after).to_string())
+    .bind(current_request_id())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// Keeps only the top-level fields that differ between the two snapshots
+fn changes(before: Option<Value>, after: Option<Value>) -> Value {
+    let mut before = as_object(before);
+    let mut after = as_object(after);
+    for field in IGNORED_FIELDS.iter().chain(PRIVATE_FIELDS.iter()) {
+        before.remove(*field);
+        after.remove(*field);
+    }
+
+    if !before.is_empty() && !after.is_empty() {
+        let unchanged: Vec<String> = before
+            .iter()
+            .filter(|(field, value)| after.get(*field) == Some(*value))
+            .map(|(field, _)| field.clone())
+            .collect();
+        for field in unchanged {
+            before.remove(&field);
+            after.remove(&field);
+        }
+    }
+
+    serde_json::json!({ "before": before, "after": after })
+}
+
+fn as_object(value: Option<Value>) -> Map<String, Value> {
+    match value {
+        Some(Value::Object(object)) => object,
+        _ => Map::new(),
+    }
+}
+
+// Changes to a contact and its addresses, newest first
+pub async fn contact_history(
+    pool: &MySqlPool,
+    username: &str,
+    contact_id: i32,
+    req: ContactHistoryRequest,
+) -> AppResult<AuditLogResponse> {
+    address_book_service::check_contact_access(pool, username, contact_id, BookRole::Viewer).await?;
+
+    query_entries(
+        pool,
+        vec!["contact_id = ?".to_string()],
+        vec![contact_id.to_string()],
+        req.page,
+        req.size,
+    )
+    .await
+}
+
+pub async fn search(pool: &MySqlPool, user: &User, req: SearchAuditRequest) -> AppResult<AuditLogResponse> {
+    if !user.is_admin {
+        return Err(AppError::Forbidden("admin access is required".to_string()));
+    }
+
+    let mut where_clauses = Vec::new();
+    let mut query_params = Vec::new();
+
+    let exact = [
+        ("actor", req.actor),
+        ("entity", req.entity),
+        ("entity_id", req.entity_id),
+        ("action", req.action.map(|action| action.as_str().to_string())),
+        ("request_id", req.request_id),
+    ];
+    for (column, value) in exact {
+        if let Some(value) = value {
+            where_clauses.push(format!("{} = ?", column));
+            query_params.push(value);
+        }
+    }
+
+    if let Some(since) = req.since {
+        where_clauses.push("created_at >= ?".to_string());
+        query_params.push(since.format(TIME_FORMAT).to_string());
+    }
+    if let Some(until) = req.until {
+        where_clauses.push("created_at < ?".to_string());
+        query_params.push(until.format(TIME_FORMAT).to_string());
+    }
+
+    query_entries(pool, where_clauses, query_params, req.page, req.size).await
+}
+
+async fn query_entries(
+    pool: &MySqlPool,
+    mut where_clauses: Vec<String>,
+    query_params: Vec<String>,
+    page: Option<i32>,
+    size: Option<i32>,
+) -> AppResult<AuditLogResponse> {
+    let page = page.unwrap_or(1).max(1);
+    let size = size.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * size;
+
+    if where_clauses.is_empty() {
+        where_clauses.push("TRUE".to_string());
+    }
+    let where_clause = where_clauses.join(" AND ");
+
+    let count_query = format!("SELECT COUNT(*) FROM audit_log WHERE {}", where_clause);
+    let mut count_query_builder = sqlx::query_as::<_, (i64,)>(&count_query);
+    for param in &query_params {
+        count_query_builder = count_query_builder.bind(param);
+    }
+    let total_item = count_query_builder.fetch_one(pool).await?.0;
+
+    let select_query = format!(
+        "SELECT id, actor, entity, entity_id, contact_id, action, changes, request_id, created_at
+         FROM audit_log
+         WHERE {}
+         ORDER BY id DESC
+         LIMIT ? OFFSET ?",
+        where_clause
+    );
+    let mut query_builder = sqlx::query_as::<_, AuditEntry>(&select_query);
+    for param in &query_params {
+        query_builder = query_builder.bind(param);
+    }
+    let entries = query_builder.bind(size).bind(offset).fetch_all(pool).await?;
+
+    let total_page = ((total_item as f64) / (size as f64)).ceil() as i32;
+
+    Ok(AuditLogResponse {
+        data: entries.into_iter().map(|entry| entry.into()).collect(),
+        paging: PagingResponse {
+            page: Some(page),
+            total_page,
+            total_item,
+        },
+    })
+}
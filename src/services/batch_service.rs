@@ -0,0 +1,148 @@
+use axum::http::StatusCode;
+use phonenumber::country;
+use sqlx::{MySqlConnection, MySqlPool};
+use crate::{
+    errors::{AppError, AppResult},
+    etag::IfMatch,
+    models::*,
+    services::contact_service,
+    validation::validate_request,
+};
+
+const MAX_OPERATIONS: usize = 500;
+
+// Runs a list of contact changes in one request. Each operation gets its
+// own transaction unless the batch is atomic, in which case the first
+// failure rolls every operation back.
+pub async fn run(
+    pool: &MySqlPool,
+    region: country::Id,
+    require_if_match: bool,
+    username: &str,
+    req: BatchContactRequest,
+) -> AppResult<BatchContactResponse> {
+    if req.operations.is_empty() || req.operations.len() > MAX_OPERATIONS {
+        return Err(AppError::Validation(format!(
+            "operations must have between 1 and {} entries",
+            MAX_OPERATIONS
+        )));
+    }
+
+    if req.atomic {
+        return run_atomic(pool, region, require_if_match, username, req.operations).await;
+    }
+
+    let mut results = Vec::new();
+    for (index, operation) in req.operations.into_iter().enumerate() {
+        let id = target_id(&operation);
+        let mut tx = pool.begin().await?;
+        let outcome = apply(&mut tx, region, require_if_match, username, operation).await;
+        if outcome.is_ok() {
+            tx.commit().await?;
+        }
+        results.push(operation_result(index, id, outcome));
+    }
+
+    Ok(BatchContactResponse { committed: true, results })
+}
+
+async fn run_atomic(
+    pool: &MySqlPool,
+    region: country::Id,
+    require_if_match: bool,
+    username: &str,
+    operations: Vec<BatchOperation>,
+) -> AppResult<BatchContactResponse> {
+    let targets: Vec<Option<i32>> = operations.iter().map(target_id).collect();
+    let mut tx = pool.begin().await?;
+
+    let mut results = Vec::new();
+    for (index, operation) in operations.into_iter().enumerate() {
+        let outcome = apply(&mut tx, region, require_if_match, username, operation).await;
+        if let Err(e) = outcome {
+            tx.rollback().await?;
+
+            // Earlier operations were undone and later ones never ran
+            let mut results: Vec<_> = targets.iter().enumerate().map(|(i, id)| not_applied(i, *id)).collect();
+            results[index] = operation_result(index, targets[index], Err(e));
+
+            return Ok(BatchContactResponse { committed: false, results });
+        }
+        results.push(operation_result(index, targets[index], outcome));
+    }
+
+    tx.commit().await?;
+
+    Ok(BatchContactResponse { committed: true, results })
+}
+
+fn target_id(operation: &BatchOperation) -> Option<i32> {
+    match operation {
+        BatchOperation::Create { .. } => None,
+        BatchOperation::Update { id, .. } | BatchOperation::Delete { id, .. } => Some(*id),
+    }
+}
+
+// Same checks as the single-contact endpoints
+async fn apply(
+    conn: &mut MySqlConnection,
+    region: country::Id,
+    require_if_match: bool,
+    username: &str,
+    operation: BatchOperation,
+) -> AppResult<Option<ContactResponse>> {
+    match operation {
+        BatchOperation::Create { data } => {
+            validate_request(&data)?;
+            let contact = contact_service::create_in(conn, region, username, data).await?;
+            Ok(Some(contact))
+        }
+        BatchOperation::Update { id, data, version } => {
+            validate_request(&data)?;
+            let if_match = IfMatch::from_version(version, require_if_match)?;
+            let contact = contact_service::update_in(conn, region, username, id, data, &if_match).await?;
+            Ok(Some(contact))
+        }
+        BatchOperation::Delete { id, version } => {
+            let if_match = IfMatch::from_version(version, require_if_match)?;
+            contact_service::remove_in(conn, username, id, &if_match).await?;
+            Ok(None)
+        }
+    }
+}
+
+fn operation_result(
+    index: usize,
+    id: Option<i32>,
+    outcome: AppResult<Option<ContactResponse>>,
+) -> BatchOperationResult {
+    match outcome {
+        Ok(data) => BatchOperationResult {
+            index,
+            status: StatusCode::OK.as_u16(),
+            id: data.as_ref().map(|contact| contact.id).or(id),
+            data,
+            errors: None,
+        },
+        Err(e) => {
+            let (status, message) = e.into_parts();
+            BatchOperationResult {
+                index,
+                status: status.as_u16(),
+                id,
+                data: None,
+                errors: Some(message),
+            }
+        }
+    }
+}
+
+fn not_applied(index: usize, id: Option<i32>) -> BatchOperationResult {
+    BatchOperationResult {
+        index,
+        status: StatusCode::FAILED_DEPENDENCY.as_u16(),
+        id,
+        data: None,
+        errors: Some("not applied, another operation in the batch failed".to_string()),
+    }
+}
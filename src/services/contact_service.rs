@@ -1,34 +1,140 @@
-use sqlx::MySqlPool;
+use std::collections::{BTreeMap, HashMap};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use phonenumber::country;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, MySqlConnection, MySqlPool, Row};
 use crate::{
     errors::{AppError, AppResult},
+    etag::IfMatch,
+    merge_patch,
     models::*,
+    phone,
+    search_query::{self, ADDRESS_FULLTEXT, CONTACT_FULLTEXT},
+    services::{
+        address_book_service, address_service,
+        audit_service::{self, AuditTarget},
+        custom_field_service, revision_service, webhook_service,
+    },
+    validation::{validate_custom_fields, validate_patched},
 };
 
+// Email or phone entry resolved from a create/update request
+struct ContactEntry {
+    value: String,
+    normalized: Option<String>,
+    label: String,
+    is_primary: bool,
+}
+
+struct EntryTable {
+    table: &'static str,
+    column: &'static str,
+    normalized_column: Option<&'static str>,
+}
+
+const EMAILS: EntryTable = EntryTable {
+    table: "contact_emails",
+    column: "email",
+    normalized_column: None,
+};
+
+const PHONES: EntryTable = EntryTable {
+    table: "contact_phones",
+    column: "phone",
+    normalized_column: Some("e164"),
+};
+
+pub const CONTACT_COLUMNS: &str =
+    "id, first_name, last_name, email, phone, phone_e164, username, address_book_id, version, created_at, updated_at";
+
+// Timestamps in cursors, in the UTC session time zone sqlx uses
+const CURSOR_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+// Columns search results can be sorted on
+const SORT_COLUMNS: [&str; 5] = ["first_name", "last_name", "email", "created_at", "updated_at"];
+
 pub async fn create(
     pool: &MySqlPool,
+    region: country::Id,
     username: &str,
     req: CreateContactRequest,
 ) -> AppResult<ContactResponse> {
+    let mut tx = pool.begin().await?;
+    let contact = create_in(&mut tx, region, username, req).await?;
+    tx.commit().await?;
+
+    Ok(contact)
+}
+
+// `create` within an open transaction
+pub async fn create_in(
+    conn: &mut MySqlConnection,
+    region: country::Id,
+    username: &str,
+    req: CreateContactRequest,
+) -> AppResult<ContactResponse> {
+    let id = insert(conn, region, username, req).await?;
+
+    let contact = find_in(conn, username, id).await?;
+    revision_service::record(conn, username, id, None).await?;
+    audit_service::record(
+        conn,
+        username,
+        AuditAction::Create,
+        AuditTarget::Contact(id),
+        None,
+        Some(audit_service::snapshot(&contact)),
+    )
+    .await?;
+    webhook_service::enqueue(conn, WebhookEvent::ContactCreated, id, &contact).await?;
+
+    Ok(contact)
+}
+
+// Inserts a contact together with its emails and phones, returning its id.
+async fn insert(
+    conn: &mut MySqlConnection,
+    region: country::Id,
+    username: &str,
+    req: CreateContactRequest,
+) -> AppResult<i32> {
+    let book_id = match req.address_book_id {
+        Some(book_id) => {
+            address_book_service::check_book_access(&mut *conn, username, book_id, BookRole::Editor).await?;
+            book_id
+        }
+        None => address_book_service::default_book(conn, username).await?,
+    };
+
+    let emails = resolve_emails(req.email, req.emails)?;
+    let phones = resolve_phones(region, req.phone, req.phones, &[])?;
+    let primary_phone = primary_entry(&phones);
+
+    // Required fields are enforced even when no values are sent
+    let definitions = custom_field_service::definitions(&mut *conn, username).await?;
+    let custom_values = validate_custom_fields(&definitions, &req.custom_fields.unwrap_or_default())?;
+
     let result = sqlx::query(
-        "INSERT INTO contacts (first_name, last_name, email, phone, username) VALUES (?, ?, ?, ?, ?)"
+        "INSERT INTO contacts (first_name, last_name, email, phone, phone_e164, username, address_book_id) 
+         VALUES (?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&req.first_name)
     .bind(&req.last_name)
-    .bind(&req.email)
-    .bind(&req.phone)
+    .bind(primary_entry(&emails).map(|e| &e.value))
+    .bind(primary_phone.map(|p| &p.value))
+    .bind(primary_phone.and_then(|p| p.normalized.as_ref()))
     .bind(username)
-    .execute(pool)
+    .bind(book_id)
+    .execute(&mut *conn)
     .await?;
 
     let id = result.last_insert_id() as i32;
 
-    Ok(ContactResponse {
-        id,
-        first_name: req.first_name,
-        last_name: req.last_name,
-        email: req.email,
-        phone: req.phone,
-    })
+    insert_entries(conn, &EMAILS, id, &emails).await?;
+    insert_entries(conn, &PHONES, id, &phones).await?;
+    custom_field_service::replace_values(conn, username, id, &custom_values).await?;
+
+    Ok(id)
 }
 
 pub async fn get(
@@ -36,95 +142,303 @@ pub async fn get(
     username: &str,
     contact_id: i32,
 ) -> AppResult<ContactResponse> {
-    let contact = sqlx::query_as::<_, Contact>(
-        "SELECT id, first_name, last_name, email, phone, username 
-         FROM contacts 
-         WHERE id = ? AND username = ?"
-    )
-    .bind(contact_id)
-    .bind(username)
-    .fetch_optional(pool)
-    .await?
-    .ok_or(AppError::NotFound("contact is not found".to_string()))?;
+    address_book_service::check_contact_access(pool, username, contact_id, BookRole::Viewer).await?;
 
-    Ok(contact.into())
+    find(pool, username, contact_id).await
 }
 
 pub async fn update(
     pool: &MySqlPool,
+    region: country::Id,
     username: &str,
     contact_id: i32,
     req: UpdateContactRequest,
+    if_match: &IfMatch,
+) -> AppResult<ContactResponse> {
+    let mut tx = pool.begin().await?;
+    let contact = update_in(&mut tx, region, username, contact_id, req, if_match).await?;
+    tx.commit().await?;
+
+    Ok(contact)
+}
+
+// `update` within an open transaction
+pub async fn update_in(
+    conn: &mut MySqlConnection,
+    region: country::Id,
+    username: &str,
+    contact_id: i32,
+    req: UpdateContactRequest,
+    if_match: &IfMatch,
 ) -> AppResult<ContactResponse> {
     // Check if contact exists
-    let count: (i64,) = sqlx::query_as(
-        "SELECT COUNT(*) FROM contacts WHERE id = ? AND username = ?"
+    address_book_service::check_contact_access(&mut *conn, username, contact_id, BookRole::Editor).await?;
+
+    check_version(conn, contact_id, if_match).await?;
+    save_update(conn, region, username, contact_id, req).await
+}
+
+// Applies a JSON merge patch to the contact as an update request would
+// describe it. Emails and phones follow `email`/`phone` unless the patch
+// sends a full list, and only the caller's custom fields can be patched.
+pub async fn patch(
+    pool: &MySqlPool,
+    region: country::Id,
+    username: &str,
+    contact_id: i32,
+    mut patch: serde_json::Value,
+    if_match: &IfMatch,
+) -> AppResult<ContactResponse> {
+    let mut tx = pool.begin().await?;
+    address_book_service::check_contact_access(&mut *tx, username, contact_id, BookRole::Editor).await?;
+    check_version(&mut tx, contact_id, if_match).await?;
+
+    let current = find_in(&mut tx, username, contact_id).await?;
+    let document = serde_json::json!({
+        "first_name": current.first_name,
+        "last_name": current.last_name,
+        "email": current.email,
+        "phone": current.phone,
+        "custom_fields": current.custom_fields,
+    });
+
+    // A removed list leaves no entries rather than falling back to the
+    // single field
+    for list in ["emails", "phones"] {
+        if let Some(value @ serde_json::Value::Null) = patch.get_mut(list) {
+            *value = serde_json::Value::Array(Vec::new());
+        }
+    }
+
+    let mut req: UpdateContactRequest = merge_patch::merge(document, &patch)?;
+    validate_patched(&req, |field| patch.get(field).is_some())?;
+
+    // Left out custom fields are kept rather than checked and rewritten
+    if patch.get("custom_fields").is_none() {
+        req.custom_fields = None;
+    }
+
+    let contact = save_update(&mut tx, region, username, contact_id, req).await?;
+    tx.commit().await?;
+
+    Ok(contact)
+}
+
+// Updates a locked contact, recording the revision and audit entry
+async fn save_update(
+    conn: &mut MySqlConnection,
+    region: country::Id,
+    username: &str,
+    contact_id: i32,
+    req: UpdateContactRequest,
+) -> AppResult<ContactResponse> {
+    let before = find_in(conn, username, contact_id).await?;
+    revision_service::record_initial(conn, contact_id).await?;
+
+    apply_update(conn, region, username, contact_id, req).await?;
+
+    let contact = find_in(conn, username, contact_id).await?;
+    revision_service::record(conn, username, contact_id, None).await?;
+    audit_service::record(
+        conn,
+        username,
+        AuditAction::Update,
+        AuditTarget::Contact(contact_id),
+        Some(audit_service::snapshot(&before)),
+        Some(audit_service::snapshot(&contact)),
     )
-    .bind(contact_id)
-    .bind(username)
-    .fetch_one(pool)
     .await?;
+    webhook_service::enqueue(conn, WebhookEvent::ContactUpdated, contact_id, &contact).await?;
 
-    if count.0 != 1 {
-        return Err(AppError::NotFound("contact is not found".to_string()));
+    Ok(contact)
+}
+
+// Writes an update's changes without checking access or recording them,
+// for callers that take care of both
+pub async fn apply_update(
+    conn: &mut MySqlConnection,
+    region: country::Id,
+    username: &str,
+    contact_id: i32,
+    req: UpdateContactRequest,
+) -> AppResult<()> {
+    // A full list replaces every entry, otherwise only the primary entry
+    // follows the single email/phone field and the others are kept
+    let replace_emails = req.emails.is_some();
+    let emails = resolve_emails(req.email, req.emails)?;
+    if replace_emails {
+        replace_entries(conn, &EMAILS, contact_id, &emails).await?;
+    } else {
+        replace_primary(conn, &EMAILS, contact_id, emails.first()).await?;
+    }
+
+    let stored: Vec<(String, Option<String>)> =
+        sqlx::query_as("SELECT phone, e164 FROM contact_phones WHERE contact_id = ?")
+            .bind(contact_id)
+            .fetch_all(&mut *conn)
+            .await?;
+
+    let replace_phones = req.phones.is_some();
+    let phones = resolve_phones(region, req.phone, req.phones, &stored)?;
+    if replace_phones {
+        replace_entries(conn, &PHONES, contact_id, &phones).await?;
+    } else {
+        replace_primary(conn, &PHONES, contact_id, phones.first()).await?;
+    }
+
+    let primary_phone = primary_entry(&phones);
+
+    if let Some(values) = &req.custom_fields {
+        let definitions = custom_field_service::definitions(&mut *conn, username).await?;
+        let custom_values = validate_custom_fields(&definitions, values)?;
+        custom_field_service::replace_values(conn, username, contact_id, &custom_values).await?;
     }
 
     // Update contact
     sqlx::query(
         "UPDATE contacts 
-         SET first_name = ?, last_name = ?, email = ?, phone = ? 
+         SET first_name = ?, last_name = ?, email = ?, phone = ?, phone_e164 = ?, 
+             version = version + 1, updated_at = CURRENT_TIMESTAMP 
          WHERE id = ?"
     )
     .bind(&req.first_name)
     .bind(&req.last_name)
-    .bind(&req.email)
-    .bind(&req.phone)
+    .bind(primary_entry(&emails).map(|e| &e.value))
+    .bind(primary_phone.map(|p| &p.value))
+    .bind(primary_phone.and_then(|p| p.normalized.as_ref()))
     .bind(contact_id)
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
-    Ok(ContactResponse {
-        id: contact_id,
-        first_name: req.first_name,
-        last_name: req.last_name,
-        email: req.email,
-        phone: req.phone,
-    })
+    Ok(())
+}
+
+// Locks the contact for the rest of the transaction and compares its
+// version with the caller's If-Match
+pub async fn check_version(conn: &mut MySqlConnection, contact_id: i32, if_match: &IfMatch) -> AppResult<()> {
+    let (version,): (i32,) = sqlx::query_as("SELECT version FROM contacts WHERE id = ? AND deleted_at IS NULL FOR UPDATE")
+        .bind(contact_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(AppError::NotFound("contact is not found".to_string()))?;
+
+    if_match.check(version)
 }
 
 pub async fn remove(
     pool: &MySqlPool,
     username: &str,
     contact_id: i32,
+    if_match: &IfMatch,
+) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+    remove_in(&mut tx, username, contact_id, if_match).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// `remove` within an open transaction
+pub async fn remove_in(
+    conn: &mut MySqlConnection,
+    username: &str,
+    contact_id: i32,
+    if_match: &IfMatch,
 ) -> AppResult<()> {
+    address_book_service::check_contact_access(&mut *conn, username, contact_id, BookRole::Editor).await?;
+
+    check_version(conn, contact_id, if_match).await?;
+
+    // Moved to the trash, see `trash_service`
     let result = sqlx::query(
-        "DELETE FROM contacts WHERE id = ? AND username = ?"
+        "UPDATE contacts SET deleted_at = CURRENT_TIMESTAMP, version = version + 1 WHERE id = ? AND deleted_at IS NULL"
     )
     .bind(contact_id)
-    .bind(username)
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
     if result.rows_affected() == 0 {
         return Err(AppError::NotFound("contact is not found".to_string()));
     }
 
+    let contact = find_in(conn, username, contact_id).await?;
+    audit_service::record(
+        conn,
+        username,
+        AuditAction::Delete,
+        AuditTarget::Contact(contact_id),
+        Some(audit_service::snapshot(&contact)),
+        None,
+    )
+    .await?;
+    webhook_service::enqueue(conn, WebhookEvent::ContactDeleted, contact_id, &contact).await?;
+
     Ok(())
 }
 
+pub async fn restore(
+    pool: &MySqlPool,
+    username: &str,
+    contact_id: i32,
+) -> AppResult<ContactResponse> {
+    let mut tx = pool.begin().await?;
+    address_book_service::check_deleted_contact_access(&mut *tx, username, contact_id, BookRole::Editor).await?;
+
+    let result = sqlx::query(
+        "UPDATE contacts SET deleted_at = NULL, version = version + 1 WHERE id = ? AND deleted_at IS NOT NULL"
+    )
+    .bind(contact_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("contact is not found".to_string()));
+    }
+
+    let contact = find_in(&mut tx, username, contact_id).await?;
+    audit_service::record(
+        &mut tx,
+        username,
+        AuditAction::Restore,
+        AuditTarget::Contact(contact_id),
+        None,
+        Some(audit_service::snapshot(&contact)),
+    )
+    .await?;
+    webhook_service::enqueue(&mut tx, WebhookEvent::ContactRestored, contact_id, &contact).await?;
+
+    tx.commit().await?;
+
+    Ok(contact)
+}
+
 pub async fn search(
     pool: &MySqlPool,
+    region: country::Id,
     username: &str,
     req: SearchContactRequest,
 ) -> AppResult<ContactSearchResponse> {
     let page = req.page.unwrap_or(1).max(1);
-    let size = req.size.unwrap_or(10).min(100).max(1);
+    let size = req.size.unwrap_or(10).clamp(1, 100);
     let offset = (page - 1) * size;
 
     // Build WHERE clause
-    let mut where_clauses = vec!["username = ?".to_string()];
+    let mut where_clauses = vec![
+        "deleted_at IS NULL".to_string(),
+        address_book_service::accessible_books_clause("address_book_id", BookRole::Viewer),
+    ];
     let mut query_params: Vec<String> = vec![username.to_string()];
 
+    if let Some(book_id) = req.address_book_id {
+        where_clauses.push("address_book_id = ?".to_string());
+        query_params.push(book_id.to_string());
+    }
+
+    if let Some(updated_since) = &req.updated_since {
+        where_clauses.push("updated_at >= ?".to_string());
+        query_params.push(updated_since.format(CURSOR_TIME_FORMAT).to_string());
+    }
+
     if let Some(name) = &req.name {
         where_clauses.push("(first_name LIKE ? OR last_name LIKE ?)".to_string());
         let like_pattern = format!("%{}%", name);
@@ -133,52 +447,763 @@ pub async fn search(
     }
 
     if let Some(email) = &req.email {
-        where_clauses.push("email LIKE ?".to_string());
+        where_clauses.push(
+            "EXISTS (SELECT 1 FROM contact_emails ce WHERE ce.contact_id = contacts.id AND ce.email LIKE ?)"
+                .to_string(),
+        );
         query_params.push(format!("%{}%", email));
     }
 
     if let Some(phone) = &req.phone {
-        where_clauses.push("phone LIKE ?".to_string());
+        // Match on normalized digits, falling back to the phone as entered
+        // for numbers that couldn't be normalized. Without digits the
+        // normalized number would match any phone, so only the latter is used.
+        let digits = phone::search_digits(phone, region);
         query_params.push(format!("%{}%", phone));
+        if digits.is_empty() {
+            where_clauses.push(
+                "EXISTS (SELECT 1 FROM contact_phones cp WHERE cp.contact_id = contacts.id AND cp.phone LIKE ?)"
+                    .to_string(),
+            );
+        } else {
+            where_clauses.push(
+                "EXISTS (SELECT 1 FROM contact_phones cp WHERE cp.contact_id = contacts.id 
+                 AND (cp.phone LIKE ? OR REPLACE(cp.e164, '+', '') LIKE ?))"
+                    .to_string(),
+            );
+            query_params.push(format!("%{}%", digits));
+        }
     }
 
-    let where_clause = where_clauses.join(" AND ");
+    if let Some(tag) = &req.tag {
+        let names: Vec<&str> = tag
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+
+        if !names.is_empty() {
+            let tag_query = format!(
+                "SELECT COUNT(DISTINCT t.id) FROM contact_tags ct JOIN tags t ON t.id = ct.tag_id 
+                 WHERE ct.contact_id = contacts.id AND t.username = ? AND t.name IN ({})",
+                vec!["?"; names.len()].join(", ")
+            );
+            match req.tag_mode.unwrap_or_default() {
+                TagMode::Any => where_clauses.push(format!("({}) > 0", tag_query)),
+                TagMode::All => where_clauses.push(format!("({}) = {}", tag_query, names.len())),
+            }
+            query_params.push(username.to_string());
+            query_params.extend(names.iter().map(|name| name.to_string()));
+        }
+    }
 
-    // Count total items
-    let count_query = format!("SELECT COUNT(*) FROM contacts WHERE {}", where_clause);
-    let mut count_query_builder = sqlx::query_as::<_, (i64,)>(&count_query);
-    for param in &query_params {
-        count_query_builder = count_query_builder.bind(param);
+    // `q` uses the search syntax, e.g. `city:Bandung -tag:inactive budi`.
+    // Plain words search the full-text indexes and rank the results.
+    let mut terms = Vec::new();
+    if let Some(q) = &req.q {
+        let query = search_query::parse(q)?;
+        let (condition, params) = query.to_sql(region, username)?;
+        where_clauses.push(condition);
+        query_params.extend(params);
+        terms = query.ranking_terms();
     }
-    let total_item = count_query_builder.fetch_one(pool).await?.0;
 
-    // Fetch contacts
+    if let Some(custom) = &req.custom {
+        let definitions = custom_field_service::definitions(pool, username).await?;
+
+        for filter in custom.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (name, value) = filter.split_once(':').ok_or_else(|| {
+                AppError::Validation(format!("custom filter {} must be name:value", filter))
+            })?;
+            let field = definitions
+                .iter()
+                .find(|field| field.name == name.trim())
+                .ok_or_else(|| AppError::Validation(format!("unknown custom field {}", name.trim())))?;
+            let value = value.trim();
+
+            // Text matches partially, numbers compare numerically and the
+            // other types must match the stored value exactly
+            let (condition, param) = match field.kind() {
+                CustomFieldType::Text => ("v.value LIKE ?", format!("%{}%", value)),
+                CustomFieldType::Number => (
+                    "CAST(v.value AS DECIMAL(30, 10)) = CAST(? AS DECIMAL(30, 10))",
+                    value.to_string(),
+                ),
+                _ => ("v.value = ?", value.to_string()),
+            };
+            where_clauses.push(format!(
+                "EXISTS (SELECT 1 FROM contact_custom_values v WHERE v.contact_id = contacts.id 
+                 AND v.field_id = ? AND {})",
+                condition
+            ));
+            query_params.push(field.id.to_string());
+            query_params.push(param);
+        }
+    }
+
+    let where_clause = where_clauses.join(" AND ");
+    let ranked = !terms.is_empty();
+    let sort_keys = sort_keys(req.sort.as_deref(), req.order.as_deref(), ranked)?;
+
+    // Count total items, skipped when the caller doesn't need it
+    let total_item = if req.include_total.unwrap_or(true) {
+        let count_query = format!("SELECT COUNT(*) FROM contacts WHERE {}", where_clause);
+        let mut count_query_builder = sqlx::query_as::<_, (i64,)>(&count_query);
+        for param in &query_params {
+            count_query_builder = count_query_builder.bind(param);
+        }
+        Some(count_query_builder.fetch_one(pool).await?.0)
+    } else {
+        None
+    };
+
+    // Relevance of the contact plus its best matching address
+    let (score_column, score_params) = if ranked {
+        let all_terms = terms.join(" ");
+        (
+            format!(
+                ", MATCH({}) AGAINST (? IN BOOLEAN MODE) 
+                 + COALESCE((SELECT MAX(MATCH({}) AGAINST (? IN BOOLEAN MODE)) 
+                   FROM addresses a WHERE a.contact_id = contacts.id AND a.deleted_at IS NULL), 0) AS score",
+                CONTACT_FULLTEXT, ADDRESS_FULLTEXT
+            ),
+            vec![all_terms.clone(), all_terms],
+        )
+    } else {
+        (String::new(), Vec::new())
+    };
+
+    // A cursor continues after the last contact of the previous page
+    // instead of skipping rows with OFFSET
+    let mut keyset_where = "TRUE".to_string();
+    let mut keyset_params = Vec::new();
+    let offset = match &req.cursor {
+        Some(cursor) => {
+            let cursor = decode_cursor(cursor, &sort_keys)?;
+            (keyset_where, keyset_params) = keyset_condition(&sort_keys, cursor.values);
+            0
+        }
+        None => offset,
+    };
+
+    // Fetch contacts, one more than the page size to know whether a next
+    // page exists. The derived table lets the cursor compare on the score.
     let select_query = format!(
-        "SELECT id, first_name, last_name, email, phone, username 
-         FROM contacts 
+        "SELECT * FROM (
+             SELECT {}{} 
+             FROM contacts 
+             WHERE {}
+         ) AS contacts 
          WHERE {} 
+         ORDER BY {} 
          LIMIT ? OFFSET ?",
-        where_clause
+        CONTACT_COLUMNS,
+        score_column,
+        where_clause,
+        keyset_where,
+        sort_keys
+            .iter()
+            .map(|key| format!("{} {}", key.expression(), if key.descending { "DESC" } else { "ASC" }))
+            .collect::<Vec<_>>()
+            .join(", ")
     );
 
-    let mut query_builder = sqlx::query_as::<_, Contact>(&select_query);
-    for param in &query_params {
+    let mut query_builder = sqlx::query(&select_query);
+    for param in score_params.iter().chain(&query_params).chain(&keyset_params) {
         query_builder = query_builder.bind(param);
     }
-    query_builder = query_builder.bind(size).bind(offset);
+    query_builder = query_builder.bind(size + 1).bind(offset);
+
+    let mut rows = Vec::new();
+    for row in query_builder.fetch_all(pool).await? {
+        let score: Option<f64> = if ranked { Some(row.try_get("score")?) } else { None };
+        rows.push((Contact::from_row(&row)?, score));
+    }
 
-    let contacts = query_builder.fetch_all(pool).await?;
+    let next_cursor = if rows.len() > size as usize {
+        rows.truncate(size as usize);
+        rows.last().map(|(last, score)| encode_cursor(&sort_keys, last, *score))
+    } else {
+        None
+    };
 
-    let data: Vec<ContactResponse> = contacts.into_iter().map(|c| c.into()).collect();
+    let (contacts, scores): (Vec<Contact>, Vec<Option<f64>>) = rows.into_iter().unzip();
+    let mut data = to_responses(&mut *pool.acquire().await?, username, contacts).await?;
+    if ranked {
+        let mut matched = matched_fields(pool, &data, &terms).await?;
+        for (contact, score) in data.iter_mut().zip(scores) {
+            contact.score = score;
+            contact.matched_fields = matched.remove(&contact.id);
+        }
+    }
 
-    let total_page = ((total_item as f64) / (size as f64)).ceil() as i32;
+    let paging = total_item.map(|total_item| PagingResponse {
+        page: req.cursor.is_none().then_some(page),
+        total_page: ((total_item as f64) / (size as f64)).ceil() as i32,
+        total_item,
+    });
 
     Ok(ContactSearchResponse {
         data,
-        paging: PagingResponse {
-            page,
-            total_page,
-            total_item,
-        },
+        paging,
+        next_cursor,
     })
-}
\ No newline at end of file
+}
+
+struct SortKey {
+    column: &'static str,
+    descending: bool,
+}
+
+impl SortKey {
+    // Nullable columns sort as empty strings so the keyset comparison
+    // matches the ORDER BY
+    fn expression(&self) -> String {
+        match self.column {
+            "last_name" | "email" => format!("COALESCE({}, '')", self.column),
+            column => column.to_string(),
+        }
+    }
+
+    fn value(&self, contact: &Contact, score: Option<f64>) -> String {
+        match self.column {
+            "score" => score.unwrap_or_default().to_string(),
+            "first_name" => contact.first_name.clone(),
+            "last_name" => contact.last_name.clone().unwrap_or_default(),
+            "email" => contact.email.clone().unwrap_or_default(),
+            "created_at" => contact.created_at.format(CURSOR_TIME_FORMAT).to_string(),
+            "updated_at" => contact.updated_at.format(CURSOR_TIME_FORMAT).to_string(),
+            _ => contact.id.to_string(),
+        }
+    }
+}
+
+// Only whitelisted column names end up in the query. `id` is always the
+// last key so rows with equal values keep the same order across pages.
+// Ranked (`q`) searches can also sort on `score` and do so by default.
+fn sort_keys(sort: Option<&str>, order: Option<&str>, ranked: bool) -> AppResult<Vec<SortKey>> {
+    let split = |list: Option<&str>| -> Vec<String> {
+        list.unwrap_or_default()
+            .split(',')
+            .map(|item| item.trim().to_lowercase())
+            .filter(|item| !item.is_empty())
+            .collect()
+    };
+    let mut keys = split(sort);
+    let directions = split(order);
+    let columns: Vec<&'static str> = SORT_COLUMNS
+        .into_iter()
+        .chain(ranked.then_some("score"))
+        .collect();
+
+    if directions.len() > keys.len().max(1) {
+        return Err(AppError::Validation("order has more entries than sort".to_string()));
+    }
+    if keys.is_empty() && ranked {
+        keys.push("score".to_string());
+    }
+
+    let mut sort_keys = Vec::new();
+    for (index, key) in keys.iter().enumerate() {
+        let column = columns
+            .iter()
+            .find(|column| **column == key)
+            .ok_or_else(|| {
+                AppError::Validation(format!("sort must be one of {}", columns.join(", ")))
+            })?;
+        // Best matches come first unless asked otherwise
+        let descending = match directions.get(index) {
+            None => *column == "score",
+            direction => is_descending(direction)?,
+        };
+        sort_keys.push(SortKey { column, descending });
+    }
+
+    // Without sort keys the order applies to the id itself
+    let descending = sort_keys.is_empty() && is_descending(directions.first())?;
+    sort_keys.push(SortKey { column: "id", descending });
+
+    Ok(sort_keys)
+}
+
+fn is_descending(direction: Option<&String>) -> AppResult<bool> {
+    match direction.map(String::as_str) {
+        None | Some("asc") => Ok(false),
+        Some("desc") => Ok(true),
+        Some(_) => Err(AppError::Validation("order must be asc or desc".to_string())),
+    }
+}
+
+// Rows after the cursor in sort order: (a > ?) OR (a = ? AND b > ?) OR ...
+fn keyset_condition(sort_keys: &[SortKey], values: Vec<String>) -> (String, Vec<String>) {
+    let mut alternatives = Vec::new();
+    let mut params = Vec::new();
+
+    for (index, key) in sort_keys.iter().enumerate() {
+        let mut conditions = Vec::new();
+        for (previous, value) in sort_keys[..index].iter().zip(&values) {
+            conditions.push(format!("{} = ?", previous.expression()));
+            params.push(value.clone());
+        }
+        conditions.push(format!("{} {} ?", key.expression(), if key.descending { "<" } else { ">" }));
+        params.push(values[index].clone());
+        alternatives.push(format!("({})", conditions.join(" AND ")));
+    }
+
+    (format!("({})", alternatives.join(" OR ")), params)
+}
+
+// Cursors are opaque to clients: base64 encoded JSON with the sort it was
+// made for and the sort values of the last contact on the page.
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    sort: Vec<String>,
+    values: Vec<String>,
+}
+
+fn encode_cursor(sort_keys: &[SortKey], last: &Contact, score: Option<f64>) -> String {
+    let cursor = Cursor {
+        sort: sort_keys.iter().map(cursor_key).collect(),
+        values: sort_keys.iter().map(|key| key.value(last, score)).collect(),
+    };
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+}
+
+fn decode_cursor(cursor: &str, sort_keys: &[SortKey]) -> AppResult<Cursor> {
+    let invalid = || AppError::BadRequest("cursor is not valid".to_string());
+
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let cursor: Cursor = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+
+    let sort: Vec<String> = sort_keys.iter().map(cursor_key).collect();
+    if cursor.sort != sort || cursor.values.len() != sort.len() {
+        return Err(AppError::BadRequest("cursor was made for a different sort".to_string()));
+    }
+
+    Ok(cursor)
+}
+
+fn cursor_key(key: &SortKey) -> String {
+    format!("{}:{}", key.column, if key.descending { "desc" } else { "asc" })
+}
+
+// Names the fields that contain one of the terms, addresses prefixed with
+// `address.`
+async fn matched_fields(
+    pool: &MySqlPool,
+    contacts: &[ContactResponse],
+    terms: &[String],
+) -> AppResult<HashMap<i32, Vec<String>>> {
+    let needles: Vec<String> = terms
+        .iter()
+        .map(|term| term.trim_matches(|c| c == '"' || c == '*').to_lowercase())
+        .collect();
+    let matches = |value: Option<&str>| {
+        value.is_some_and(|value| {
+            let value = value.to_lowercase();
+            needles.iter().any(|needle| value.contains(needle.as_str()))
+        })
+    };
+
+    let mut result: HashMap<i32, Vec<String>> = HashMap::new();
+    for contact in contacts {
+        let fields = [
+            ("first_name", Some(contact.first_name.as_str())),
+            ("last_name", contact.last_name.as_deref()),
+            ("email", contact.email.as_deref()),
+            ("phone", contact.phone.as_deref()),
+        ];
+        result.insert(
+            contact.id,
+            fields
+                .into_iter()
+                .filter(|(_, value)| matches(*value))
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        );
+    }
+
+    if contacts.is_empty() {
+        return Ok(result);
+    }
+
+    let query = format!(
+        "SELECT {} FROM addresses WHERE contact_id IN ({}) AND deleted_at IS NULL",
+        address_service::ADDRESS_COLUMNS,
+        vec!["?"; contacts.len()].join(", ")
+    );
+    let mut query_builder = sqlx::query_as::<_, Address>(&query);
+    for contact in contacts {
+        query_builder = query_builder.bind(contact.id);
+    }
+    for address in query_builder.fetch_all(pool).await? {
+        let fields = [
+            ("address.street", address.street.as_deref()),
+            ("address.city", address.city.as_deref()),
+            ("address.province", address.province.as_deref()),
+            ("address.country", Some(address.country.as_str())),
+            ("address.postal_code", Some(address.postal_code.as_str())),
+        ];
+        let matched = result.entry(address.contact_id).or_default();
+        for (name, value) in fields {
+            if matches(value) && !matched.iter().any(|field| field == name) {
+                matched.push(name.to_string());
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+// Tags and custom field values are the viewer's own
+pub async fn find(pool: &MySqlPool, username: &str, contact_id: i32) -> AppResult<ContactResponse> {
+    find_in(&mut *pool.acquire().await?, username, contact_id).await
+}
+
+// Same as `find`, reading within an open transaction
+pub async fn find_in(conn: &mut MySqlConnection, username: &str, contact_id: i32) -> AppResult<ContactResponse> {
+    find_many_in(conn, username, &[contact_id])
+        .await?
+        .pop()
+        .ok_or(AppError::NotFound("contact is not found".to_string()))
+}
+
+// Loads contacts by id without an ownership check, for ids the caller has
+// already been authorized for.
+pub async fn find_many(pool: &MySqlPool, username: &str, ids: &[i32]) -> AppResult<Vec<ContactResponse>> {
+    find_many_in(&mut *pool.acquire().await?, username, ids).await
+}
+
+pub async fn find_many_in(
+    conn: &mut MySqlConnection,
+    username: &str,
+    ids: &[i32],
+) -> AppResult<Vec<ContactResponse>> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let query = format!(
+        "SELECT {} FROM contacts WHERE id IN ({}) ORDER BY id",
+        CONTACT_COLUMNS,
+        vec!["?"; ids.len()].join(", ")
+    );
+    let mut query_builder = sqlx::query_as::<_, Contact>(&query);
+    for id in ids {
+        query_builder = query_builder.bind(id);
+    }
+    let contacts = query_builder.fetch_all(&mut *conn).await?;
+
+    to_responses(conn, username, contacts).await
+}
+
+// Builds responses for a page of contacts, loading their emails, phones,
+// tags, custom field values and primary addresses with one query per table.
+pub async fn to_responses(
+    conn: &mut MySqlConnection,
+    username: &str,
+    contacts: Vec<Contact>,
+) -> AppResult<Vec<ContactResponse>> {
+    if contacts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let placeholders = vec!["?"; contacts.len()].join(", ");
+
+    let emails_query = format!(
+        "SELECT id, contact_id, email, label, is_primary FROM contact_emails 
+         WHERE contact_id IN ({}) ORDER BY is_primary DESC, id",
+        placeholders
+    );
+    let mut emails_builder = sqlx::query_as::<_, ContactEmail>(&emails_query);
+    for contact in &contacts {
+        emails_builder = emails_builder.bind(contact.id);
+    }
+    let mut emails: HashMap<i32, Vec<ContactEmailResponse>> = HashMap::new();
+    for email in emails_builder.fetch_all(&mut *conn).await? {
+        emails.entry(email.contact_id).or_default().push(email.into());
+    }
+
+    let phones_query = format!(
+        "SELECT id, contact_id, phone, e164, label, is_primary FROM contact_phones 
+         WHERE contact_id IN ({}) ORDER BY is_primary DESC, id",
+        placeholders
+    );
+    let mut phones_builder = sqlx::query_as::<_, ContactPhone>(&phones_query);
+    for contact in &contacts {
+        phones_builder = phones_builder.bind(contact.id);
+    }
+    let mut phones: HashMap<i32, Vec<ContactPhoneResponse>> = HashMap::new();
+    for phone in phones_builder.fetch_all(&mut *conn).await? {
+        phones.entry(phone.contact_id).or_default().push(phone.into());
+    }
+
+    // Tags are private, so only the viewer's own are shown
+    let tags_query = format!(
+        "SELECT ct.contact_id, t.id, t.name FROM contact_tags ct 
+         JOIN tags t ON t.id = ct.tag_id 
+         WHERE t.username = ? AND ct.contact_id IN ({}) ORDER BY t.name",
+        placeholders
+    );
+    let mut tags_builder = sqlx::query_as::<_, (i32, i32, String)>(&tags_query).bind(username);
+    for contact in &contacts {
+        tags_builder = tags_builder.bind(contact.id);
+    }
+    let mut tags: HashMap<i32, Vec<TagResponse>> = HashMap::new();
+    for (contact_id, id, name) in tags_builder.fetch_all(&mut *conn).await? {
+        tags.entry(contact_id).or_default().push(TagResponse { id, name });
+    }
+
+    // Field names are only unique per user, so other members' values of a
+    // shared contact are left out
+    let custom_query = format!(
+        "SELECT v.contact_id, f.name, f.field_type, v.value FROM contact_custom_values v 
+         JOIN custom_fields f ON f.id = v.field_id 
+         WHERE f.username = ? AND v.contact_id IN ({})",
+        placeholders
+    );
+    let mut custom_builder = sqlx::query_as::<_, (i32, String, String, String)>(&custom_query).bind(username);
+    for contact in &contacts {
+        custom_builder = custom_builder.bind(contact.id);
+    }
+    let mut custom_fields: HashMap<i32, BTreeMap<String, serde_json::Value>> = HashMap::new();
+    for (contact_id, name, field_type, value) in custom_builder.fetch_all(&mut *conn).await? {
+        custom_fields
+            .entry(contact_id)
+            .or_default()
+            .insert(name, custom_field_service::to_json(&field_type, &value));
+    }
+
+    let addresses_query = format!(
+        "SELECT {} FROM addresses WHERE contact_id IN ({}) AND is_primary = TRUE AND deleted_at IS NULL",
+        address_service::ADDRESS_COLUMNS,
+        placeholders
+    );
+    let mut addresses_builder = sqlx::query_as::<_, Address>(&addresses_query);
+    for contact in &contacts {
+        addresses_builder = addresses_builder.bind(contact.id);
+    }
+    let mut primary_addresses: HashMap<i32, AddressResponse> = HashMap::new();
+    for address in addresses_builder.fetch_all(&mut *conn).await? {
+        primary_addresses.insert(address.contact_id, address.into());
+    }
+
+    Ok(contacts
+        .into_iter()
+        .map(|contact| {
+            let id = contact.id;
+            let mut response = ContactResponse::from(contact);
+            response.emails = emails.remove(&id).unwrap_or_default();
+            response.phones = phones.remove(&id).unwrap_or_default();
+            response.tags = tags.remove(&id).unwrap_or_default();
+            response.custom_fields = custom_fields.remove(&id).unwrap_or_default();
+            response.primary_address = primary_addresses.remove(&id);
+            response
+        })
+        .collect())
+}
+
+fn resolve_emails(
+    email: Option<String>,
+    emails: Option<Vec<ContactEmailRequest>>,
+) -> AppResult<Vec<ContactEntry>> {
+    let entries = emails.map(|list| {
+        list.into_iter()
+            .map(|e| (e.email, e.label, e.is_primary))
+            .collect()
+    });
+    resolve_entries(email, entries, "email")
+}
+
+// Phones the contact already has keep their stored normalization, so
+// numbers saved before they were validated don't block other changes
+fn resolve_phones(
+    region: country::Id,
+    phone: Option<String>,
+    phones: Option<Vec<ContactPhoneRequest>>,
+    stored: &[(String, Option<String>)],
+) -> AppResult<Vec<ContactEntry>> {
+    let entries = phones.map(|list| {
+        list.into_iter()
+            .map(|p| (p.phone, p.label, p.is_primary))
+            .collect()
+    });
+    let mut resolved = resolve_entries(phone, entries, "phone")?;
+
+    for entry in &mut resolved {
+        if let Some((_, e164)) = stored.iter().find(|(phone, _)| *phone == entry.value) {
+            entry.normalized = e164.clone();
+            continue;
+        }
+
+        let normalized = phone::to_e164(&entry.value, region).ok_or_else(|| {
+            AppError::Validation(format!("phone {} is not a valid phone number", entry.value))
+        })?;
+        entry.normalized = Some(normalized);
+    }
+
+    Ok(resolved)
+}
+
+// When a list is given it wins over the single field; the first entry
+// becomes primary if none is marked.
+fn resolve_entries(
+    single: Option<String>,
+    list: Option<Vec<(String, Option<String>, bool)>>,
+    kind: &str,
+) -> AppResult<Vec<ContactEntry>> {
+    let Some(list) = list else {
+        return Ok(single
+            .map(|value| ContactEntry {
+                value,
+                normalized: None,
+                label: "other".to_string(),
+                is_primary: true,
+            })
+            .into_iter()
+            .collect());
+    };
+
+    let primaries = list.iter().filter(|(_, _, is_primary)| *is_primary).count();
+    if primaries > 1 {
+        return Err(AppError::Validation(format!("only one {} can be primary", kind)));
+    }
+
+    Ok(list
+        .into_iter()
+        .enumerate()
+        .map(|(index, (value, label, is_primary))| ContactEntry {
+            value,
+            normalized: None,
+            label: label.unwrap_or_else(|| "other".to_string()),
+            is_primary: is_primary || (primaries == 0 && index == 0),
+        })
+        .collect())
+}
+
+fn primary_entry(entries: &[ContactEntry]) -> Option<&ContactEntry> {
+    entries.iter().find(|entry| entry.is_primary)
+}
+
+async fn insert_entries(
+    conn: &mut MySqlConnection,
+    table: &EntryTable,
+    contact_id: i32,
+    entries: &[ContactEntry],
+) -> AppResult<()> {
+    let query = match table.normalized_column {
+        Some(normalized) => format!(
+            "INSERT INTO {} (contact_id, {}, {}, label, is_primary) VALUES (?, ?, ?, ?, ?)",
+            table.table, table.column, normalized
+        ),
+        None => format!(
+            "INSERT INTO {} (contact_id, {}, label, is_primary) VALUES (?, ?, ?, ?)",
+            table.table, table.column
+        ),
+    };
+
+    for entry in entries {
+        let mut query_builder = sqlx::query(&query).bind(contact_id).bind(&entry.value);
+        if table.normalized_column.is_some() {
+            query_builder = query_builder.bind(&entry.normalized);
+        }
+        query_builder
+            .bind(&entry.label)
+            .bind(entry.is_primary)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn replace_entries(
+    conn: &mut MySqlConnection,
+    table: &EntryTable,
+    contact_id: i32,
+    entries: &[ContactEntry],
+) -> AppResult<()> {
+    sqlx::query(&format!("DELETE FROM {} WHERE contact_id = ?", table.table))
+        .bind(contact_id)
+        .execute(&mut *conn)
+        .await?;
+
+    insert_entries(conn, table, contact_id, entries).await
+}
+
+// Replaces the primary entry, keeping its label
+async fn replace_primary(
+    conn: &mut MySqlConnection,
+    table: &EntryTable,
+    contact_id: i32,
+    primary: Option<&ContactEntry>,
+) -> AppResult<()> {
+    let label: Option<(String,)> = sqlx::query_as(&format!(
+        "SELECT label FROM {} WHERE contact_id = ? AND is_primary = TRUE",
+        table.table
+    ))
+    .bind(contact_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    sqlx::query(&format!(
+        "DELETE FROM {} WHERE contact_id = ? AND is_primary = TRUE",
+        table.table
+    ))
+    .bind(contact_id)
+    .execute(&mut *conn)
+    .await?;
+
+    if let Some(primary) = primary {
+        let entry = ContactEntry {
+            value: primary.value.clone(),
+            normalized: primary.normalized.clone(),
+            label: label.map(|l| l.0).unwrap_or_else(|| primary.label.clone()),
+            is_primary: true,
+        };
+        insert_entries(conn, table, contact_id, &[entry]).await?;
+    }
+
+    Ok(())
+}
+
+// Fills in E.164 forms for phones stored before normalization existed or
+// under a different default region. Runs at startup, but only touches
+// phones without one; numbers that don't parse stay as is.
+pub async fn normalize_phones(pool: &MySqlPool, region: country::Id) -> AppResult<u64> {
+    let mut normalized = 0;
+
+    let phones: Vec<(i32, String)> =
+        sqlx::query_as("SELECT id, phone FROM contact_phones WHERE e164 IS NULL")
+            .fetch_all(pool)
+            .await?;
+    for (id, raw) in phones {
+        if let Some(e164) = phone::to_e164(&raw, region) {
+            sqlx::query("UPDATE contact_phones SET e164 = ? WHERE id = ?")
+                .bind(&e164)
+                .bind(id)
+                .execute(pool)
+                .await?;
+            normalized += 1;
+        }
+    }
+
+    let contacts: Vec<(i32, String)> = sqlx::query_as(
+        "SELECT id, phone FROM contacts WHERE phone IS NOT NULL AND phone_e164 IS NULL"
+    )
+    .fetch_all(pool)
+    .await?;
+    for (id, raw) in contacts {
+        if let Some(e164) = phone::to_e164(&raw, region) {
+            // Not a change to the contact, so `updated_at` is kept from
+            // moving on by its ON UPDATE default
+            sqlx::query("UPDATE contacts SET phone_e164 = ?, updated_at = updated_at WHERE id = ?")
+                .bind(&e164)
+                .bind(id)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(normalized)
+}
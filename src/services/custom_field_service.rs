@@ -0,0 +1,210 @@
+use sqlx::{MySqlConnection, MySqlExecutor, MySqlPool};
+use crate::{
+    errors::{AppError, AppResult},
+    models::*,
+};
+
+fn check_options(field_type: CustomFieldType, options: &Option<Vec<String>>) -> AppResult<()> {
+    match (field_type, options) {
+        (CustomFieldType::Enum, Some(options)) if !options.is_empty() => Ok(()),
+        (CustomFieldType::Enum, _) => Err(AppError::Validation(
+            "enum custom fields need at least one option".to_string(),
+        )),
+        (_, Some(_)) => Err(AppError::Validation(
+            "only enum custom fields have options".to_string(),
+        )),
+        (_, None) => Ok(()),
+    }
+}
+
+async fn check_name_available(
+    pool: &MySqlPool,
+    username: &str,
+    name: &str,
+    except_id: Option<i32>,
+) -> AppResult<()> {
+    let count: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM custom_fields WHERE username = ? AND name = ? AND id <> ?"
+    )
+    .bind(username)
+    .bind(name)
+    .bind(except_id.unwrap_or(0))
+    .fetch_one(pool)
+    .await?;
+
+    if count.0 > 0 {
+        return Err(AppError::BadRequest("custom field already exists".to_string()));
+    }
+
+    Ok(())
+}
+
+// Enum options can only be dropped once no contact holds them, so stored
+// values always stay within the options
+async fn check_options_unused(pool: &MySqlPool, field_id: i32, options: &Option<Vec<String>>) -> AppResult<()> {
+    let Some(options) = options else {
+        return Ok(());
+    };
+
+    let query = format!(
+        "SELECT value FROM contact_custom_values WHERE field_id = ? AND value NOT IN ({}) LIMIT 1",
+        vec!["?"; options.len()].join(", ")
+    );
+    let mut query_builder = sqlx::query_as::<_, (String,)>(&query).bind(field_id);
+    for option in options {
+        query_builder = query_builder.bind(option);
+    }
+
+    if let Some((value,)) = query_builder.fetch_optional(pool).await? {
+        return Err(AppError::BadRequest(format!("option {} is still used by contacts", value)));
+    }
+
+    Ok(())
+}
+
+async fn find_field(pool: &MySqlPool, username: &str, field_id: i32) -> AppResult<CustomField> {
+    sqlx::query_as::<_, CustomField>(
+        "SELECT id, name, field_type, required, options, username
+         FROM custom_fields
+         WHERE id = ? AND username = ?"
+    )
+    .bind(field_id)
+    .bind(username)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AppError::NotFound("custom field is not found".to_string()))
+}
+
+pub async fn create(
+    pool: &MySqlPool,
+    username: &str,
+    req: CreateCustomFieldRequest,
+) -> AppResult<CustomFieldResponse> {
+    check_options(req.field_type, &req.options)?;
+    check_name_available(pool, username, &req.name, None).await?;
+
+    let options = req.options.as_ref().map(|o| serde_json::json!(o).to_string());
+
+    let result = sqlx::query(
+        "INSERT INTO custom_fields (name, field_type, required, options, username) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(&req.name)
+    .bind(req.field_type.as_str())
+    .bind(req.required)
+    .bind(&options)
+    .bind(username)
+    .execute(pool)
+    .await?;
+
+    Ok(CustomFieldResponse {
+        id: result.last_insert_id() as i32,
+        name: req.name,
+        field_type: req.field_type,
+        required: req.required,
+        options: req.options.unwrap_or_default(),
+    })
+}
+
+pub async fn list(pool: &MySqlPool, username: &str) -> AppResult<Vec<CustomFieldResponse>> {
+    let fields = definitions(pool, username).await?;
+    Ok(fields.into_iter().map(|f| f.into()).collect())
+}
+
+pub async fn update(
+    pool: &MySqlPool,
+    username: &str,
+    field_id: i32,
+    req: UpdateCustomFieldRequest,
+) -> AppResult<CustomFieldResponse> {
+    let field = find_field(pool, username, field_id).await?;
+    check_options(field.kind(), &req.options)?;
+    check_name_available(pool, username, &req.name, Some(field_id)).await?;
+    check_options_unused(pool, field_id, &req.options).await?;
+
+    let options = req.options.as_ref().map(|o| serde_json::json!(o).to_string());
+
+    // A newly required field applies to later writes; contacts without a
+    // value keep working until an update sends their custom fields
+    sqlx::query("UPDATE custom_fields SET name = ?, required = ?, options = ? WHERE id = ?")
+        .bind(&req.name)
+        .bind(req.required)
+        .bind(&options)
+        .bind(field_id)
+        .execute(pool)
+        .await?;
+
+    Ok(find_field(pool, username, field_id).await?.into())
+}
+
+pub async fn remove(pool: &MySqlPool, username: &str, field_id: i32) -> AppResult<()> {
+    let result = sqlx::query("DELETE FROM custom_fields WHERE id = ? AND username = ?")
+        .bind(field_id)
+        .bind(username)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("custom field is not found".to_string()));
+    }
+
+    Ok(())
+}
+
+pub async fn definitions<'e, E: MySqlExecutor<'e>>(
+    executor: E,
+    username: &str,
+) -> AppResult<Vec<CustomField>> {
+    let fields = sqlx::query_as::<_, CustomField>(
+        "SELECT id, name, field_type, required, options, username
+         FROM custom_fields
+         WHERE username = ?
+         ORDER BY name"
+    )
+    .bind(username)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(fields)
+}
+
+// Replaces the values of the user's own fields on a contact; values set by
+// other members of a shared book are left alone.
+pub async fn replace_values(
+    conn: &mut MySqlConnection,
+    username: &str,
+    contact_id: i32,
+    values: &[(i32, String)],
+) -> AppResult<()> {
+    sqlx::query(
+        "DELETE v FROM contact_custom_values v
+         JOIN custom_fields f ON f.id = v.field_id
+         WHERE v.contact_id = ? AND f.username = ?"
+    )
+    .bind(contact_id)
+    .bind(username)
+    .execute(&mut *conn)
+    .await?;
+
+    for (field_id, value) in values {
+        sqlx::query(
+            "INSERT INTO contact_custom_values (contact_id, field_id, value) VALUES (?, ?, ?)"
+        )
+        .bind(contact_id)
+        .bind(field_id)
+        .bind(value)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// Turns a stored value back into JSON according to the field type
+pub fn to_json(field_type: &str, value: &str) -> serde_json::Value {
+    match field_type.parse() {
+        Ok(CustomFieldType::Number) => serde_json::from_str(value)
+            .unwrap_or_else(|_| serde_json::Value::String(value.to_string())),
+        Ok(CustomFieldType::Bool) => serde_json::Value::Bool(value == "true"),
+        _ => serde_json::Value::String(value.to_string()),
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::{BTreeSet, HashMap};
+use sqlx::{MySqlConnection, MySqlPool};
+use crate::{
+    errors::{AppError, AppResult},
+    etag::IfMatch,
+    models::*,
+    services::{
+        address_book_service, address_service,
+        audit_service::{self, AuditTarget},
+        contact_service, revision_service, webhook_service,
+    },
+};
+
+// Full names at least this similar (Jaro-Winkler) count as the same person
+const NAME_SIMILARITY: f64 = 0.92;
+
+// Only contacts the caller can edit are compared, since duplicates are
+// meant to be merged.
+pub async fn find_duplicates(
+    pool: &MySqlPool,
+    username: &str,
+) -> AppResult<Vec<DuplicateGroupResponse>> {
+    let editable = address_book_service::accessible_books_clause("c.address_book_id", BookRole::Editor);
+
+    let contacts: Vec<(i32, String, Option<String>)> = sqlx::query_as(&format!(
+        "SELECT c.id, c.first_name, c.last_name FROM contacts c 
+         WHERE c.deleted_at IS NULL AND {} 
+         ORDER BY c.id",
+        editable
+    ))
+    .bind(username)
+    .fetch_all(pool)
+    .await?;
+
+    let emails: Vec<(i32, String)> = sqlx::query_as(&format!(
+        "SELECT ce.contact_id, LOWER(TRIM(ce.email))
+         FROM contact_emails ce
+         JOIN contacts c ON c.id = ce.contact_id
+         WHERE c.deleted_at IS NULL AND {}",
+        editable
+    ))
+    .bind(username)
+    .fetch_all(pool)
+    .await?;
+
+    let phones: Vec<(i32, String)> = sqlx::query_as(&format!(
+        "SELECT cp.contact_id, cp.e164
+         FROM contact_phones cp
+         JOIN contacts c ON c.id = cp.contact_id
+         WHERE c.deleted_at IS NULL AND {} AND cp.e164 IS NOT NULL",
+        editable
+    ))
+    .bind(username)
+    .fetch_all(pool)
+    .await?;
+
+    let position: HashMap<i32, usize> = contacts
+        .iter()
+        .enumerate()
+        .map(|(index, (id, _, _))| (*id, index))
+        .collect();
+
+    // Pairs of contact positions that match, with the reason they match
+    let mut links: Vec<(usize, usize, &str)> = Vec::new();
+    for (values, reason) in [(&emails, "email"), (&phones, "phone")] {
+        let mut first_seen: HashMap<&str, usize> = HashMap::new();
+        for (contact_id, value) in values {
+            // Contacts created after the first query are left for next time
+            let Some(&current) = position.get(contact_id) else {
+                continue;
+            };
+            match first_seen.get(value.as_str()) {
+                Some(&other) if other != current => links.push((other, current, reason)),
+                Some(_) => {}
+                None => {
+                    first_seen.insert(value, current);
+                }
+            }
+        }
+    }
+
+    let names: Vec<String> = contacts
+        .iter()
+        .map(|(_, first_name, last_name)| normalize_name(first_name, last_name.as_deref()))
+        .collect();
+    for i in 0..names.len() {
+        for j in (i + 1)..names.len() {
+            if strsim::jaro_winkler(&names[i], &names[j]) >= NAME_SIMILARITY {
+                links.push((i, j, "name"));
+            }
+        }
+    }
+
+    // Union-find over the links so transitive matches end up in one group
+    let mut parent: Vec<usize> = (0..contacts.len()).collect();
+    for &(a, b, _) in &links {
+        let (root_a, root_b) = (find_root(&mut parent, a), find_root(&mut parent, b));
+        if root_a != root_b {
+            parent[root_b.max(root_a)] = root_a.min(root_b);
+        }
+    }
+
+    let mut groups: HashMap<usize, (BTreeSet<i32>, BTreeSet<String>)> = HashMap::new();
+    for &(a, b, reason) in &links {
+        let group = groups.entry(find_root(&mut parent, a)).or_default();
+        group.0.insert(contacts[a].0);
+        group.0.insert(contacts[b].0);
+        group.1.insert(reason.to_string());
+    }
+
+    let ids: Vec<i32> = groups.values().flat_map(|(ids, _)| ids.iter().copied()).collect();
+    let mut loaded: HashMap<i32, ContactResponse> = contact_service::find_many(pool, username, &ids)
+        .await?
+        .into_iter()
+        .map(|contact| (contact.id, contact))
+        .collect();
+
+    let mut result: Vec<DuplicateGroupResponse> = groups
+        .into_values()
+        .map(|(ids, reasons)| DuplicateGroupResponse {
+            reasons: reasons.into_iter().collect(),
+            contacts: ids.iter().filter_map(|id| loaded.remove(id)).collect(),
+        })
+        .collect();
+    result.sort_by_key(|group| group.contacts.first().map(|c| c.id));
+
+    Ok(result)
+}
+
+pub async fn merge(
+    pool: &MySqlPool,
+    username: &str,
+    target_id: i32,
+    req: MergeContactRequest,
+) -> AppResult<ContactResponse> {
+    if req.source_id == target_id {
+        return Err(AppError::BadRequest("cannot merge a contact into itself".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Locked in id order, so merges in opposite directions can't deadlock
+    let first = lock_contact(&mut tx, username, target_id.min(req.source_id)).await?;
+    let second = lock_contact(&mut tx, username, target_id.max(req.source_id)).await?;
+    let (target, source) = if first.id == target_id { (first, second) } else { (second, first) };
+    revision_service::record_initial(&mut tx, target_id).await?;
+
+    let target_before = contact_service::find_in(&mut tx, username, target_id).await?;
+
+    let first_name = match req.fields.first_name {
+        MergeSide::Target => target.first_name,
+        MergeSide::Source => source.first_name,
+    };
+    let last_name = match req.fields.last_name {
+        MergeSide::Target => target.last_name,
+        MergeSide::Source => source.last_name,
+    };
+
+    merge_entries(
+        &mut tx,
+        "contact_emails",
+        "LOWER(email)",
+        "email, label",
+        target_id,
+        source.id,
+        req.fields.email,
+    )
+    .await?;
+    merge_entries(
+        &mut tx,
+        "contact_phones",
+        "COALESCE(e164, phone)",
+        "phone, e164, label",
+        target_id,
+        source.id,
+        req.fields.phone,
+    )
+    .await?;
+
+    // The single email/phone columns mirror the merged primary entries
+    sqlx::query(
+        "UPDATE contacts c
+         SET first_name = ?, last_name = ?,
+             email = (SELECT email FROM contact_emails WHERE contact_id = c.id AND is_primary = TRUE LIMIT 1),
+             phone = (SELECT phone FROM contact_phones WHERE contact_id = c.id AND is_primary = TRUE LIMIT 1),
+             phone_e164 = (SELECT e164 FROM contact_phones WHERE contact_id = c.id AND is_primary = TRUE LIMIT 1),
+             version = version + 1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?"
+    )
+    .bind(&first_name)
+    .bind(&last_name)
+    .bind(target_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT IGNORE INTO contact_tags (contact_id, tag_id) 
+         SELECT ?, tag_id FROM contact_tags WHERE contact_id = ?"
+    )
+    .bind(target_id)
+    .bind(source.id)
+    .execute(&mut *tx)
+    .await?;
+
+    // Values the target already has win over the source's
+    sqlx::query(
+        "INSERT IGNORE INTO contact_custom_values (contact_id, field_id, value) 
+         SELECT ?, field_id, value FROM contact_custom_values WHERE contact_id = ?"
+    )
+    .bind(target_id)
+    .bind(source.id)
+    .execute(&mut *tx)
+    .await?;
+
+    // The target keeps its primary address; the source's only becomes
+    // primary when the target has none
+    let target_primaries: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM addresses WHERE contact_id = ? AND is_primary = TRUE AND deleted_at IS NULL"
+    )
+    .bind(target_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    address_service::move_all(&mut tx, username, source.id, target_id, target_primaries.0 == 0).await?;
+
+    contact_service::remove_in(&mut tx, username, source.id, &IfMatch::Any).await?;
+
+    let merged = contact_service::find_in(&mut tx, username, target_id).await?;
+    revision_service::record(&mut tx, username, target_id, None).await?;
+    audit_service::record(
+        &mut tx,
+        username,
+        AuditAction::Merge,
+        AuditTarget::Contact(target_id),
+        Some(audit_service::snapshot(&target_before)),
+        Some(audit_service::snapshot(&merged)),
+    )
+    .await?;
+    webhook_service::enqueue(&mut tx, WebhookEvent::ContactUpdated, target_id, &merged).await?;
+
+    tx.commit().await?;
+
+    Ok(merged)
+}
+
+async fn lock_contact(
+    conn: &mut MySqlConnection,
+    username: &str,
+    contact_id: i32,
+) -> AppResult<Contact> {
+    address_book_service::check_contact_access(&mut *conn, username, contact_id, BookRole::Editor).await?;
+
+    let contact = sqlx::query_as::<_, Contact>(&format!(
+        "SELECT {}
+         FROM contacts
+         WHERE id = ?
+         FOR UPDATE",
+        contact_service::CONTACT_COLUMNS
+    ))
+    .bind(contact_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(contact)
+}
+
+// Copies the source's entries onto the target, skipping ones the target
+// already has, and keeps the primary entry of the chosen side (or of the
+// other side when the chosen one has none).
+async fn merge_entries(
+    conn: &mut MySqlConnection,
+    table: &str,
+    key: &str,
+    columns: &str,
+    target_id: i32,
+    source_id: i32,
+    primary_from: MergeSide,
+) -> AppResult<()> {
+    let entries: Vec<(i32, i32, String, bool)> = sqlx::query_as(&format!(
+        "SELECT id, contact_id, {}, is_primary FROM {} WHERE contact_id IN (?, ?) ORDER BY id",
+        key, table
+    ))
+    .bind(target_id)
+    .bind(source_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut surviving: HashMap<&str, i32> = entries
+        .iter()
+        .filter(|(_, contact_id, _, _)| *contact_id == target_id)
+        .map(|(id, _, key, _)| (key.as_str(), *id))
+        .collect();
+
+    for (id, contact_id, key, _) in &entries {
+        if *contact_id != source_id {
+            continue;
+        }
+        if !surviving.contains_key(key.as_str()) {
+            let result = sqlx::query(&format!(
+                "INSERT INTO {0} (contact_id, {1}, is_primary) SELECT ?, {1}, FALSE FROM {0} WHERE id = ?",
+                table, columns
+            ))
+            .bind(target_id)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+            surviving.insert(key, result.last_insert_id() as i32);
+        }
+    }
+
+    let primary_of = |contact_id: i32| {
+        entries
+            .iter()
+            .find(|(_, owner, _, is_primary)| *owner == contact_id && *is_primary)
+            .map(|(_, _, key, _)| surviving[key.as_str()])
+    };
+    let (chosen, other) = match primary_from {
+        MergeSide::Target => (target_id, source_id),
+        MergeSide::Source => (source_id, target_id),
+    };
+    let primary_id = primary_of(chosen).or_else(|| primary_of(other));
+
+    sqlx::query(&format!("UPDATE {} SET is_primary = COALESCE(id = ?, FALSE) WHERE contact_id = ?", table))
+        .bind(primary_id)
+        .bind(target_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+fn normalize_name(first_name: &str, last_name: Option<&str>) -> String {
+    format!("{} {}", first_name, last_name.unwrap_or_default())
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+use phonenumber::country;
+use sqlx::MySqlPool;
+use validator::Validate;
+use crate::{
+    errors::{AppError, AppResult},
+    geocoder::Geocoder,
+    models::*,
+    phone,
+    services::{address_service, contact_service},
+    vcard::{VCard, VCardAddress},
+};
+
+const SUPPORTED_VERSIONS: [&str; 2] = ["3.0", "4.0"];
+
+pub async fn import_vcard(
+    pool: &MySqlPool,
+    geocoder: &Arc<dyn Geocoder>,
+    region: country::Id,
+    username: &str,
+    address_book_id: Option<i32>,
+    cards: Vec<VCard>,
+) -> AppResult<ContactImportResponse> {
+    let mut ids = Vec::new();
+    let mut issues = Vec::new();
+    let mut skipped = 0;
+
+    for (index, card) in cards.into_iter().enumerate() {
+        let entry = index + 1;
+        let name = card.display_name();
+        let mut report = |message: String| {
+            issues.push(ImportIssue { entry, name: name.clone(), message });
+        };
+
+        if let Some(version) = &card.version {
+            if !SUPPORTED_VERSIONS.contains(&version.as_str()) {
+                report(format!("unsupported vCard version {}", version));
+                skipped += 1;
+                continue;
+            }
+        }
+
+        let Some((first_name, last_name)) = contact_name(&card) else {
+            report("entry has no N or FN name".to_string());
+            skipped += 1;
+            continue;
+        };
+
+        let mut emails: Vec<ContactEmailRequest> = Vec::new();
+        for value in card.emails {
+            let email = ContactEmailRequest {
+                label: Some(email_label(&value.types).to_string()),
+                is_primary: value.preferred && !emails.iter().any(|e| e.is_primary),
+                email: value.value,
+            };
+            match email.validate() {
+                Ok(()) => emails.push(email),
+                Err(_) => report(format!("EMAIL {} is not valid", email.email)),
+            }
+        }
+
+        let mut phones: Vec<ContactPhoneRequest> = Vec::new();
+        for value in card.phones {
+            let phone = ContactPhoneRequest {
+                label: Some(phone_label(&value.types).to_string()),
+                is_primary: value.preferred && !phones.iter().any(|p| p.is_primary),
+                phone: value.value,
+            };
+            match phone.validate() {
+                Ok(()) if phone::to_e164(&phone.phone, region).is_some() => phones.push(phone),
+                _ => report(format!("TEL {} is not valid", phone.phone)),
+            }
+        }
+
+        let contact_req = CreateContactRequest {
+            first_name,
+            last_name,
+            email: None,
+            phone: None,
+            emails: Some(emails),
+            phones: Some(phones),
+            address_book_id,
+            custom_fields: None,
+        };
+        if let Err(errors) = contact_req.validate() {
+            report(format!("name could not be mapped: {}", errors));
+            skipped += 1;
+            continue;
+        }
+
+        let mut address_reqs = Vec::new();
+        for (position, adr) in card.addresses.into_iter().enumerate() {
+            match map_address(adr) {
+                Ok(req) => address_reqs.push(req),
+                Err(message) => report(format!("ADR #{} {}", position + 1, message)),
+            }
+        }
+
+        // Custom fields the user marked as required can't be filled from a vCard
+        match insert_contact(pool, geocoder, region, username, contact_req, address_reqs).await {
+            Ok(id) => ids.push(id),
+            Err(AppError::Validation(message)) => {
+                report(message);
+                skipped += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let contacts = contact_service::find_many(pool, username, &ids).await?;
+
+    Ok(ContactImportResponse {
+        imported: contacts.len(),
+        skipped,
+        contacts,
+        issues,
+    })
+}
+
+// Prefers the structured N property and falls back to splitting FN on its
+// last space, e.g. "Budi Santoso" becomes ("Budi", Some("Santoso")).
+fn contact_name(card: &VCard) -> Option<(String, Option<String>)> {
+    match (&card.given_name, &card.family_name) {
+        (Some(given), family) => return Some((given.clone(), family.clone())),
+        (None, Some(family)) => return Some((family.clone(), None)),
+        (None, None) => {}
+    }
+
+    let formatted = card.formatted_name.as_ref()?;
+    match formatted.rsplit_once(' ') {
+        Some((first, last)) => Some((first.trim().to_string(), Some(last.to_string()))),
+        None => Some((formatted.clone(), None)),
+    }
+}
+
+fn map_address(adr: VCardAddress) -> Result<CreateAddressRequest, String> {
+    let (Some(country), Some(postal_code)) = (adr.country, adr.postal_code) else {
+        return Err("is missing a country or postal code".to_string());
+    };
+
+    let req = CreateAddressRequest {
+        street: adr.street,
+        city: adr.locality,
+        province: adr.region,
+        country,
+        postal_code,
+        address_type: None,
+        is_primary: false,
+    };
+
+    req.validate()
+        .map_err(|e| format!("could not be mapped: {}", e))?;
+
+    Ok(req)
+}
+
+async fn insert_contact(
+    pool: &MySqlPool,
+    geocoder: &Arc<dyn Geocoder>,
+    region: country::Id,
+    username: &str,
+    req: CreateContactRequest,
+    addresses: Vec<CreateAddressRequest>,
+) -> AppResult<i32> {
+    // Same as creating the contact and its addresses through the API
+    let mut tx = pool.begin().await?;
+
+    let id = contact_service::create_in(&mut tx, region, username, req).await?.id;
+
+    let mut address_ids = Vec::new();
+    for address in &addresses {
+        address_ids.push(address_service::create_in(&mut tx, username, id, address).await?.id);
+    }
+
+    tx.commit().await?;
+
+    for address_id in address_ids {
+        address_service::geocode_later(pool, geocoder, address_id);
+    }
+
+    Ok(id)
+}
+
+fn email_label(types: &[String]) -> &'static str {
+    if types.iter().any(|t| t == "work") {
+        "work"
+    } else if types.iter().any(|t| t == "home") {
+        "home"
+    } else {
+        "other"
+    }
+}
+
+fn phone_label(types: &[String]) -> &'static str {
+    if types.iter().any(|t| t == "cell" || t == "mobile") {
+        "mobile"
+    } else {
+        email_label(types)
+    }
+}
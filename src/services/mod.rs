@@ -1,3 +1,14 @@
 pub mod user_service;
 pub mod contact_service;
-pub mod address_service;
\ No newline at end of file
+pub mod address_service;
+pub mod import_service;
+pub mod duplicate_service;
+pub mod tag_service;
+pub mod address_book_service;
+pub mod custom_field_service;
+pub mod saved_search_service;
+pub mod trash_service;
+pub mod audit_service;
+pub mod revision_service;
+pub mod batch_service;
+pub mod webhook_service;
@@ -0,0 +1,254 @@
+use std::sync::Arc;
+use phonenumber::country;
+use sqlx::{MySqlConnection, MySqlPool};
+use crate::{
+    errors::{AppError, AppResult},
+    geocoder::Geocoder,
+    models::*,
+    services::{
+        address_book_service, address_service,
+        audit_service::{self, AuditTarget},
+        contact_service, custom_field_service, webhook_service,
+    },
+};
+
+// Current state of a contact and its addresses, with the custom field
+// values of `username`
+async fn snapshot(conn: &mut MySqlConnection, username: &str, contact_id: i32) -> AppResult<ContactSnapshot> {
+    let contact = contact_service::find_in(conn, username, contact_id).await?;
+
+    let addresses = sqlx::query_as::<_, Address>(&format!(
+        "SELECT {} FROM addresses WHERE contact_id = ? AND deleted_at IS NULL ORDER BY id",
+        address_service::ADDRESS_COLUMNS
+    ))
+    .bind(contact_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(ContactSnapshot {
+        first_name: contact.first_name,
+        last_name: contact.last_name,
+        emails: contact
+            .emails
+            .into_iter()
+            .map(|e| ContactEmailRequest { email: e.email, label: Some(e.label), is_primary: e.is_primary })
+            .collect(),
+        phones: contact
+            .phones
+            .into_iter()
+            .map(|p| ContactPhoneRequest { phone: p.phone, label: Some(p.label), is_primary: p.is_primary })
+            .collect(),
+        custom_fields: contact.custom_fields,
+        addresses: addresses
+            .into_iter()
+            .map(|a| AddressSnapshot {
+                id: a.id,
+                address: CreateAddressRequest {
+                    street: a.street,
+                    city: a.city,
+                    province: a.province,
+                    country: a.country,
+                    postal_code: a.postal_code,
+                    address_type: Some(a.address_type),
+                    is_primary: a.is_primary,
+                },
+            })
+            .collect(),
+    })
+}
+
+// Stores the contact's current state as its next revision
+pub async fn record(
+    conn: &mut MySqlConnection,
+    username: &str,
+    contact_id: i32,
+    reverted_from: Option<i32>,
+) -> AppResult<i32> {
+    let snapshot = serde_json::to_string(&snapshot(conn, username, contact_id).await?).map_err(|e| {
+        tracing::error!("{}", e);
+        AppError::Internal
+    })?;
+
+    let (revision,): (i32,) = sqlx::query_as(
+        "SELECT COALESCE(MAX(revision), 0) + 1 FROM contact_revisions WHERE contact_id = ?"
+    )
+    .bind(contact_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO contact_revisions (contact_id, revision, snapshot, username, reverted_from)
+         VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(contact_id)
+    .bind(revision)
+    .bind(&snapshot)
+    .bind(username)
+    .bind(reverted_from)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(revision)
+}
+
+// Called before changing a contact. Locks it so revisions are numbered in
+// order, and keeps the state of contacts created before revisions existed
+// as their first revision, credited to the contact's creator.
+pub async fn record_initial(conn: &mut MySqlConnection, contact_id: i32) -> AppResult<()> {
+    let (creator,): (String,) = sqlx::query_as("SELECT username FROM contacts WHERE id = ? FOR UPDATE")
+        .bind(contact_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM contact_revisions WHERE contact_id = ?")
+        .bind(contact_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if count == 0 {
+        record(conn, &creator, contact_id, None).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn list(
+    pool: &MySqlPool,
+    username: &str,
+    contact_id: i32,
+) -> AppResult<Vec<ContactRevisionResponse>> {
+    address_book_service::check_contact_access(pool, username, contact_id, BookRole::Viewer).await?;
+
+    let revisions = sqlx::query_as::<_, ContactRevision>(
+        "SELECT revision, snapshot, username, reverted_from, created_at
+         FROM contact_revisions
+         WHERE contact_id = ?
+         ORDER BY revision DESC"
+    )
+    .bind(contact_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(revisions
+        .into_iter()
+        .map(|r| ContactRevisionResponse {
+            revision: r.revision,
+            username: r.username,
+            reverted_from: r.reverted_from,
+            created_at: r.created_at,
+            snapshot: None,
+        })
+        .collect())
+}
+
+async fn find_revision(
+    conn: &mut MySqlConnection,
+    contact_id: i32,
+    revision: i32,
+) -> AppResult<(ContactRevision, ContactSnapshot)> {
+    let found = sqlx::query_as::<_, ContactRevision>(
+        "SELECT revision, snapshot, username, reverted_from, created_at
+         FROM contact_revisions
+         WHERE contact_id = ? AND revision = ?"
+    )
+    .bind(contact_id)
+    .bind(revision)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(AppError::NotFound("revision is not found".to_string()))?;
+
+    let snapshot = serde_json::from_str(&found.snapshot).map_err(|e| {
+        tracing::error!("revision {} of contact {} is not readable: {}", revision, contact_id, e);
+        AppError::Internal
+    })?;
+
+    Ok((found, snapshot))
+}
+
+pub async fn get(
+    pool: &MySqlPool,
+    username: &str,
+    contact_id: i32,
+    revision: i32,
+) -> AppResult<ContactRevisionResponse> {
+    address_book_service::check_contact_access(pool, username, contact_id, BookRole::Viewer).await?;
+
+    let (found, mut snapshot) = find_revision(&mut *pool.acquire().await?, contact_id, revision).await?;
+    if found.username != username {
+        snapshot.custom_fields.clear();
+    }
+
+    Ok(ContactRevisionResponse {
+        revision: found.revision,
+        username: found.username,
+        reverted_from: found.reverted_from,
+        created_at: found.created_at,
+        snapshot: Some(snapshot),
+    })
+}
+
+// Puts the contact and its addresses back the way they were at `revision`
+// and records the result as a new revision. A revision holds the custom
+// field values of the member who made it, so they are only put back when
+// that member reverts; other members' values are left as they are.
+pub async fn revert(
+    pool: &MySqlPool,
+    geocoder: &Arc<dyn Geocoder>,
+    region: country::Id,
+    username: &str,
+    contact_id: i32,
+    revision: i32,
+) -> AppResult<ContactResponse> {
+    let mut tx = pool.begin().await?;
+    address_book_service::check_contact_access(&mut *tx, username, contact_id, BookRole::Editor).await?;
+    record_initial(&mut tx, contact_id).await?;
+
+    let (found, snapshot) = find_revision(&mut tx, contact_id, revision).await?;
+    let before = contact_service::find_in(&mut tx, username, contact_id).await?;
+
+    let custom_fields = if found.username == username {
+        let definitions = custom_field_service::definitions(&mut *tx, username).await?;
+        Some(
+            snapshot
+                .custom_fields
+                .into_iter()
+                .filter(|(name, _)| definitions.iter().any(|field| &field.name == name))
+                .collect(),
+        )
+    } else {
+        None
+    };
+
+    let req = UpdateContactRequest {
+        first_name: snapshot.first_name,
+        last_name: snapshot.last_name,
+        email: None,
+        phone: None,
+        emails: Some(snapshot.emails),
+        phones: Some(snapshot.phones),
+        custom_fields,
+    };
+    contact_service::apply_update(&mut tx, region, username, contact_id, req).await?;
+    let changed = address_service::replace_all(&mut tx, username, contact_id, &snapshot.addresses).await?;
+
+    let contact = contact_service::find_in(&mut tx, username, contact_id).await?;
+    record(&mut tx, username, contact_id, Some(revision)).await?;
+    audit_service::record(
+        &mut tx,
+        username,
+        AuditAction::Revert,
+        AuditTarget::Contact(contact_id),
+        Some(audit_service::snapshot(&before)),
+        Some(audit_service::snapshot(&contact)),
+    )
+    .await?;
+    webhook_service::enqueue(&mut tx, WebhookEvent::ContactUpdated, contact_id, &contact).await?;
+
+    tx.commit().await?;
+
+    for address_id in changed {
+        address_service::geocode_later(pool, geocoder, address_id);
+    }
+
+    Ok(contact)
+}
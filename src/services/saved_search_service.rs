@@ -0,0 +1,165 @@
+use phonenumber::country;
+use sqlx::MySqlPool;
+use crate::{
+    errors::{AppError, AppResult},
+    models::*,
+    search_query,
+    services::contact_service,
+};
+
+async fn check_name_available(
+    pool: &MySqlPool,
+    username: &str,
+    name: &str,
+    except_id: Option<i32>,
+) -> AppResult<()> {
+    let count: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM saved_searches WHERE username = ? AND name = ? AND id <> ?"
+    )
+    .bind(username)
+    .bind(name)
+    .bind(except_id.unwrap_or(0))
+    .fetch_one(pool)
+    .await?;
+
+    if count.0 > 0 {
+        return Err(AppError::BadRequest("saved search already exists".to_string()));
+    }
+
+    Ok(())
+}
+
+async fn find_search(pool: &MySqlPool, username: &str, search_id: i32) -> AppResult<SavedSearch> {
+    sqlx::query_as::<_, SavedSearch>(
+        "SELECT id, name, filters, username FROM saved_searches WHERE id = ? AND username = ?"
+    )
+    .bind(search_id)
+    .bind(username)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AppError::NotFound("saved search is not found".to_string()))
+}
+
+// Drops paging and unset filters, and checks the query parses so broken
+// searches are rejected when saved rather than when run.
+fn filters_json(filters: SearchContactRequest) -> AppResult<String> {
+    if let Some(q) = &filters.q {
+        search_query::parse(q)?;
+    }
+
+    let filters = SearchContactRequest {
+        cursor: None,
+        include_total: None,
+        page: None,
+        size: None,
+        ..filters
+    };
+
+    let mut value = serde_json::to_value(filters).map_err(|e| {
+        tracing::error!("{}", e);
+        AppError::Internal
+    })?;
+    if let Some(object) = value.as_object_mut() {
+        object.retain(|_, v| !v.is_null());
+    }
+
+    Ok(value.to_string())
+}
+
+pub async fn create(
+    pool: &MySqlPool,
+    username: &str,
+    req: SavedSearchRequest,
+) -> AppResult<SavedSearchResponse> {
+    check_name_available(pool, username, &req.name, None).await?;
+    let filters = filters_json(req.filters)?;
+
+    let result = sqlx::query("INSERT INTO saved_searches (name, filters, username) VALUES (?, ?, ?)")
+        .bind(&req.name)
+        .bind(&filters)
+        .bind(username)
+        .execute(pool)
+        .await?;
+
+    Ok(find_search(pool, username, result.last_insert_id() as i32).await?.into())
+}
+
+pub async fn list(pool: &MySqlPool, username: &str) -> AppResult<Vec<SavedSearchResponse>> {
+    let searches = sqlx::query_as::<_, SavedSearch>(
+        "SELECT id, name, filters, username FROM saved_searches WHERE username = ? ORDER BY name"
+    )
+    .bind(username)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(searches.into_iter().map(|s| s.into()).collect())
+}
+
+pub async fn get(
+    pool: &MySqlPool,
+    username: &str,
+    search_id: i32,
+) -> AppResult<SavedSearchResponse> {
+    Ok(find_search(pool, username, search_id).await?.into())
+}
+
+pub async fn update(
+    pool: &MySqlPool,
+    username: &str,
+    search_id: i32,
+    req: SavedSearchRequest,
+) -> AppResult<SavedSearchResponse> {
+    find_search(pool, username, search_id).await?;
+    check_name_available(pool, username, &req.name, Some(search_id)).await?;
+    let filters = filters_json(req.filters)?;
+
+    sqlx::query("UPDATE saved_searches SET name = ?, filters = ? WHERE id = ?")
+        .bind(&req.name)
+        .bind(&filters)
+        .bind(search_id)
+        .execute(pool)
+        .await?;
+
+    get(pool, username, search_id).await
+}
+
+pub async fn remove(pool: &MySqlPool, username: &str, search_id: i32) -> AppResult<()> {
+    let result = sqlx::query("DELETE FROM saved_searches WHERE id = ? AND username = ?")
+        .bind(search_id)
+        .bind(username)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("saved search is not found".to_string()));
+    }
+
+    Ok(())
+}
+
+// Runs the saved filters with the caller's paging, against the contacts the
+// caller can currently see.
+pub async fn contacts(
+    pool: &MySqlPool,
+    region: country::Id,
+    username: &str,
+    search_id: i32,
+    paging: SavedSearchContactsRequest,
+) -> AppResult<ContactSearchResponse> {
+    let search = find_search(pool, username, search_id).await?;
+
+    let filters: SearchContactRequest = serde_json::from_str(&search.filters).map_err(|e| {
+        tracing::error!("saved search {} has invalid filters: {}", search.id, e);
+        AppError::Internal
+    })?;
+
+    let req = SearchContactRequest {
+        cursor: paging.cursor,
+        include_total: paging.include_total,
+        page: paging.page,
+        size: paging.size,
+        ..filters
+    };
+
+    contact_service::search(pool, region, username, req).await
+}
@@ -0,0 +1,171 @@
+use sqlx::MySqlPool;
+use crate::{
+    errors::{AppError, AppResult},
+    etag::IfMatch,
+    models::*,
+    services::{address_book_service, contact_service, webhook_service},
+};
+
+async fn check_name_available(
+    pool: &MySqlPool,
+    username: &str,
+    name: &str,
+    except_id: Option<i32>,
+) -> AppResult<()> {
+    let count: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM tags WHERE username = ? AND name = ? AND id <> ?"
+    )
+    .bind(username)
+    .bind(name)
+    .bind(except_id.unwrap_or(0))
+    .fetch_one(pool)
+    .await?;
+
+    if count.0 > 0 {
+        return Err(AppError::BadRequest("tag already exists".to_string()));
+    }
+
+    Ok(())
+}
+
+async fn check_tag_exists(
+    pool: &MySqlPool,
+    username: &str,
+    tag_id: i32,
+) -> AppResult<()> {
+    let count: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM tags WHERE id = ? AND username = ?"
+    )
+    .bind(tag_id)
+    .bind(username)
+    .fetch_one(pool)
+    .await?;
+
+    if count.0 != 1 {
+        return Err(AppError::NotFound("tag is not found".to_string()));
+    }
+
+    Ok(())
+}
+
+pub async fn create(
+    pool: &MySqlPool,
+    username: &str,
+    req: CreateTagRequest,
+) -> AppResult<TagResponse> {
+    check_name_available(pool, username, &req.name, None).await?;
+
+    let result = sqlx::query("INSERT INTO tags (name, username) VALUES (?, ?)")
+        .bind(&req.name)
+        .bind(username)
+        .execute(pool)
+        .await?;
+
+    Ok(TagResponse {
+        id: result.last_insert_id() as i32,
+        name: req.name,
+    })
+}
+
+pub async fn list(pool: &MySqlPool, username: &str) -> AppResult<Vec<TagResponse>> {
+    let tags = sqlx::query_as::<_, Tag>(
+        "SELECT id, name, username FROM tags WHERE username = ? ORDER BY name"
+    )
+    .bind(username)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tags.into_iter().map(|t| t.into()).collect())
+}
+
+pub async fn update(
+    pool: &MySqlPool,
+    username: &str,
+    tag_id: i32,
+    req: UpdateTagRequest,
+) -> AppResult<TagResponse> {
+    check_tag_exists(pool, username, tag_id).await?;
+    check_name_available(pool, username, &req.name, Some(tag_id)).await?;
+
+    sqlx::query("UPDATE tags SET name = ? WHERE id = ?")
+        .bind(&req.name)
+        .bind(tag_id)
+        .execute(pool)
+        .await?;
+
+    Ok(TagResponse {
+        id: tag_id,
+        name: req.name,
+    })
+}
+
+pub async fn remove(pool: &MySqlPool, username: &str, tag_id: i32) -> AppResult<()> {
+    let result = sqlx::query("DELETE FROM tags WHERE id = ? AND username = ?")
+        .bind(tag_id)
+        .bind(username)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("tag is not found".to_string()));
+    }
+
+    Ok(())
+}
+
+pub async fn attach(
+    pool: &MySqlPool,
+    username: &str,
+    contact_id: i32,
+    tag_id: i32,
+) -> AppResult<ContactResponse> {
+    change_tags(pool, username, contact_id, tag_id, "INSERT IGNORE INTO contact_tags (contact_id, tag_id) VALUES (?, ?)").await
+}
+
+pub async fn detach(
+    pool: &MySqlPool,
+    username: &str,
+    contact_id: i32,
+    tag_id: i32,
+) -> AppResult<ContactResponse> {
+    change_tags(pool, username, contact_id, tag_id, "DELETE FROM contact_tags WHERE contact_id = ? AND tag_id = ?").await
+}
+
+// Tagging changes the contact, so it gets a new version (and ETag) and a
+// webhook event. Tags are private to their owner, so nothing goes into the
+// shared audit log. Nothing changes when the tag was already attached or
+// detached.
+async fn change_tags(
+    pool: &MySqlPool,
+    username: &str,
+    contact_id: i32,
+    tag_id: i32,
+    query: &str,
+) -> AppResult<ContactResponse> {
+    check_tag_exists(pool, username, tag_id).await?;
+
+    let mut tx = pool.begin().await?;
+    address_book_service::check_contact_access(&mut *tx, username, contact_id, BookRole::Editor).await?;
+    contact_service::check_version(&mut tx, contact_id, &IfMatch::Any).await?;
+
+    let result = sqlx::query(query)
+        .bind(contact_id)
+        .bind(tag_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return contact_service::find_in(&mut tx, username, contact_id).await;
+    }
+
+    sqlx::query("UPDATE contacts SET version = version + 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(contact_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let contact = contact_service::find_in(&mut tx, username, contact_id).await?;
+    webhook_service::enqueue(&mut tx, WebhookEvent::ContactUpdated, contact_id, &contact).await?;
+
+    tx.commit().await?;
+
+    Ok(contact)
+}
@@ -0,0 +1,73 @@
+use sqlx::{MySqlPool, Row};
+use crate::{
+    errors::AppResult,
+    models::*,
+    services::{address_book_service, address_service},
+};
+
+// Deleted contacts and addresses the caller could restore, most recently
+// deleted first. Addresses of deleted contacts are restored with the
+// contact, so only addresses deleted on their own are listed.
+pub async fn list(pool: &MySqlPool, username: &str) -> AppResult<TrashResponse> {
+    let editable = address_book_service::accessible_books_clause("c.address_book_id", BookRole::Editor);
+
+    let contacts = sqlx::query_as::<_, TrashedContactResponse>(&format!(
+        "SELECT c.id, c.first_name, c.last_name, c.address_book_id, c.deleted_at 
+         FROM contacts c 
+         WHERE c.deleted_at IS NOT NULL AND {} 
+         ORDER BY c.deleted_at DESC, c.id",
+        editable
+    ))
+    .bind(username)
+    .fetch_all(pool)
+    .await?;
+
+    let rows = sqlx::query(&format!(
+        "SELECT {}, c.first_name, c.last_name, a.deleted_at 
+         FROM addresses a 
+         JOIN contacts c ON c.id = a.contact_id 
+         WHERE a.deleted_at IS NOT NULL AND c.deleted_at IS NULL AND {} 
+         ORDER BY a.deleted_at DESC, a.id",
+        address_service::joined_address_columns(),
+        editable
+    ))
+    .bind(username)
+    .fetch_all(pool)
+    .await?;
+
+    let mut addresses = Vec::new();
+    for row in rows {
+        addresses.push(TrashedAddressResponse {
+            deleted_at: row.try_get("deleted_at")?,
+            address: address_service::contact_address(&row)?,
+        });
+    }
+
+    Ok(TrashResponse { contacts, addresses })
+}
+
+// Permanently removes contacts and addresses that have been in the trash
+// for longer than the retention. Returns the number of rows removed.
+pub async fn purge(pool: &MySqlPool, retention_days: u32) -> AppResult<u64> {
+    let mut tx = pool.begin().await?;
+
+    let addresses = sqlx::query(
+        "DELETE a FROM addresses a 
+         JOIN contacts c ON c.id = a.contact_id 
+         WHERE a.deleted_at < NOW() - INTERVAL ? DAY 
+            OR c.deleted_at < NOW() - INTERVAL ? DAY"
+    )
+    .bind(retention_days)
+    .bind(retention_days)
+    .execute(&mut *tx)
+    .await?;
+
+    let contacts = sqlx::query("DELETE FROM contacts WHERE deleted_at < NOW() - INTERVAL ? DAY")
+        .bind(retention_days)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(addresses.rows_affected() + contacts.rows_affected())
+}
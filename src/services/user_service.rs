@@ -1,10 +1,24 @@
-use sqlx::MySqlPool;
+use sqlx::{MySqlConnection, MySqlPool};
 use uuid::Uuid;
 use crate::{
     errors::{AppError, AppResult},
     models::*,
+    services::{
+        address_book_service,
+        audit_service::{self, AuditTarget},
+    },
 };
 
+pub const USER_COLUMNS: &str = "username, password, name, token, is_admin, created_at, updated_at";
+
+async fn find_user(conn: &mut MySqlConnection, username: &str) -> AppResult<User> {
+    sqlx::query_as::<_, User>(&format!("SELECT {} FROM users WHERE username = ?", USER_COLUMNS))
+        .bind(username)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(AppError::NotFound("user is not found".to_string()))
+}
+
 pub async fn register(pool: &MySqlPool, req: RegisterRequest) -> AppResult<UserResponse> {
     // Check if username already exists
     let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE username = ?")
@@ -20,6 +34,8 @@ pub async fn register(pool: &MySqlPool, req: RegisterRequest) -> AppResult<UserR
     let hashed_password = bcrypt::hash(&req.password, bcrypt::DEFAULT_COST)
         .map_err(|_| AppError::Internal)?;
 
+    let mut tx = pool.begin().await?;
+
     // Insert user
     sqlx::query(
         "INSERT INTO users (username, password, name, token) VALUES (?, ?, ?, NULL)"
@@ -27,20 +43,33 @@ pub async fn register(pool: &MySqlPool, req: RegisterRequest) -> AppResult<UserR
     .bind(&req.username)
     .bind(&hashed_password)
     .bind(&req.name)
-    .execute(pool)
+    .execute(&mut *tx)
+    .await?;
+
+    address_book_service::create_default_book(&mut tx, &req.username).await?;
+
+    let user: UserResponse = find_user(&mut tx, &req.username).await?.into();
+    audit_service::record(
+        &mut tx,
+        &req.username,
+        AuditAction::Create,
+        AuditTarget::User(&req.username),
+        None,
+        Some(audit_service::snapshot(&user)),
+    )
     .await?;
 
-    Ok(UserResponse {
-        username: req.username,
-        name: req.name,
-    })
+    tx.commit().await?;
+
+    Ok(user)
 }
 
 pub async fn login(pool: &MySqlPool, req: LoginRequest) -> AppResult<LoginResponse> {
     // Find user
-    let user = sqlx::query_as::<_, User>(
-        "SELECT username, password, name, token FROM users WHERE username = ?"
-    )
+    let user = sqlx::query_as::<_, User>(&format!(
+        "SELECT {} FROM users WHERE username = ?",
+        USER_COLUMNS
+    ))
     .bind(&req.username)
     .fetch_optional(pool)
     .await?
@@ -72,62 +101,51 @@ pub async fn update(
     username: &str,
     req: UpdateUserRequest,
 ) -> AppResult<UserResponse> {
-    // Check if user exists
-    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE username = ?")
-        .bind(username)
-        .fetch_one(pool)
-        .await?;
+    let mut tx = pool.begin().await?;
+    let user = find_user(&mut tx, username).await?;
 
-    if count.0 != 1 {
-        return Err(AppError::NotFound("user is not found".to_string()));
+    if req.name.is_none() && req.password.is_none() {
+        // No updates, just return current user
+        return Ok(user.into());
     }
 
-    // Build update query dynamically
-    let mut updates = Vec::new();
-    let mut query = String::from("UPDATE users SET ");
+    let password = match &req.password {
+        Some(password) => bcrypt::hash(password, bcrypt::DEFAULT_COST)
+            .map_err(|_| AppError::Internal)?,
+        None => user.password.clone(),
+    };
 
-    if let Some(name) = &req.name {
-        updates.push(format!("name = '{}'", name));
-    }
+    sqlx::query(
+        "UPDATE users SET name = ?, password = ?, updated_at = CURRENT_TIMESTAMP WHERE username = ?"
+    )
+    .bind(req.name.as_ref().unwrap_or(&user.name))
+    .bind(&password)
+    .bind(username)
+    .execute(&mut *tx)
+    .await?;
 
-    if let Some(password) = &req.password {
-        let hashed = bcrypt::hash(password, bcrypt::DEFAULT_COST)
-            .map_err(|_| AppError::Internal)?;
-        updates.push(format!("password = '{}'", hashed));
-    }
+    let updated: UserResponse = find_user(&mut tx, username).await?.into();
 
-    if updates.is_empty() {
-        // No updates, just return current user
-        let user = sqlx::query_as::<_, User>(
-            "SELECT username, password, name, token FROM users WHERE username = ?"
-        )
-        .bind(username)
-        .fetch_one(pool)
-        .await?;
-        
-        return Ok(UserResponse {
-            username: user.username,
-            name: user.name,
-        });
+    // Password changes are recorded without the password itself
+    let mut before = audit_service::snapshot(&UserResponse::from(user));
+    let mut after = audit_service::snapshot(&updated);
+    if req.password.is_some() {
+        before["password_changed"] = false.into();
+        after["password_changed"] = true.into();
     }
-
-    query.push_str(&updates.join(", "));
-    query.push_str(&format!(" WHERE username = '{}'", username));
-
-    sqlx::query(&query).execute(pool).await?;
-
-    // Fetch updated user
-    let user = sqlx::query_as::<_, User>(
-        "SELECT username, password, name, token FROM users WHERE username = ?"
+    audit_service::record(
+        &mut tx,
+        username,
+        AuditAction::Update,
+        AuditTarget::User(username),
+        Some(before),
+        Some(after),
     )
-    .bind(username)
-    .fetch_one(pool)
     .await?;
 
-    Ok(UserResponse {
-        username: user.username,
-        name: user.name,
-    })
+    tx.commit().await?;
+
+    Ok(updated)
 }
 
 pub async fn logout(pool: &MySqlPool, username: &str) -> AppResult<()> {
@@ -0,0 +1,537 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::{MySqlConnection, MySqlPool, Row};
+use tokio::task::JoinSet;
+use uuid::Uuid;
+use crate::{
+    errors::{AppError, AppResult},
+    models::*,
+};
+
+const WEBHOOK_COLUMNS: &str = "id, url, events, secret, active, created_at, updated_at";
+
+// Events fanned out and deliveries attempted per dispatcher run
+const BATCH_SIZE: i64 = 50;
+// A delivery is given up after this many attempts, about an hour after
+// the first one
+const MAX_ATTEMPTS: i32 = 8;
+const FIRST_RETRY_SECS: i64 = 30;
+// A claimed batch is sent concurrently and each request times out, so this
+// is far longer than a run takes; a delivery claimed by a dispatcher that
+// died is picked up again after it
+const CLAIM_SECS: i64 = 300;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// Delivered and failed events are kept this long for the delivery log
+const RETENTION_DAYS: i64 = 30;
+
+async fn find_webhook(pool: &MySqlPool, username: &str, webhook_id: i32) -> AppResult<Webhook> {
+    sqlx::query_as::<_, Webhook>(&format!(
+        "SELECT {} FROM webhooks WHERE id = ? AND username = ?",
+        WEBHOOK_COLUMNS
+    ))
+    .bind(webhook_id)
+    .bind(username)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(AppError::NotFound("webhook is not found".to_string()))
+}
+
+// Loopback, private, link-local and other non-public addresses, which
+// webhooks must not reach unless private URLs are allowed
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+// Checked when a webhook is saved and again before every attempt. Host
+// names are checked when they are resolved, see `PublicResolver`.
+fn check_target(url: &str, allow_private: bool) -> Result<(), String> {
+    let url = reqwest::Url::parse(url).map_err(|_| "url is not a valid URL".to_string())?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("url must be an http or https URL".to_string());
+    }
+    if allow_private {
+        return Ok(());
+    }
+
+    let host = url.host_str().unwrap_or_default();
+    let private = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => !is_public(ip),
+        Err(_) => host == "localhost" || host.ends_with(".localhost"),
+    };
+    if private {
+        return Err("url must not point to a private or local address".to_string());
+    }
+
+    Ok(())
+}
+
+fn check_url(url: &str, allow_private: bool) -> AppResult<()> {
+    check_target(url, allow_private).map_err(AppError::Validation)
+}
+
+// Only hands out public addresses, so a host name can't be pointed at the
+// server's own network after its webhook was saved
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+fn events_column(events: &[WebhookEvent]) -> String {
+    let mut names: Vec<&str> = events.iter().map(|event| event.as_str()).collect();
+    names.sort();
+    names.dedup();
+    names.join(",")
+}
+
+pub async fn create(
+    pool: &MySqlPool,
+    allow_private: bool,
+    username: &str,
+    req: WebhookRequest,
+) -> AppResult<WebhookResponse> {
+    check_url(&req.url, allow_private)?;
+
+    let secret = req.secret.unwrap_or_else(|| format!("whsec_{}", Uuid::new_v4().simple()));
+
+    let result = sqlx::query(
+        "INSERT INTO webhooks (username, url, events, secret, active) VALUES (?, ?, ?, ?, ?)"
+    )
+    .bind(username)
+    .bind(&req.url)
+    .bind(events_column(&req.events))
+    .bind(&secret)
+    .bind(req.active.unwrap_or(true))
+    .execute(pool)
+    .await?;
+
+    // The secret is only shown once, so the caller can verify signatures
+    let mut webhook: WebhookResponse = find_webhook(pool, username, result.last_insert_id() as i32).await?.into();
+    webhook.secret = Some(secret);
+
+    Ok(webhook)
+}
+
+pub async fn list(pool: &MySqlPool, username: &str) -> AppResult<Vec<WebhookResponse>> {
+    let webhooks = sqlx::query_as::<_, Webhook>(&format!(
+        "SELECT {} FROM webhooks WHERE username = ? ORDER BY id",
+        WEBHOOK_COLUMNS
+    ))
+    .bind(username)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(webhooks.into_iter().map(|w| w.into()).collect())
+}
+
+pub async fn get(pool: &MySqlPool, username: &str, webhook_id: i32) -> AppResult<WebhookResponse> {
+    Ok(find_webhook(pool, username, webhook_id).await?.into())
+}
+
+pub async fn update(
+    pool: &MySqlPool,
+    allow_private: bool,
+    username: &str,
+    webhook_id: i32,
+    req: WebhookRequest,
+) -> AppResult<WebhookResponse> {
+    let webhook = find_webhook(pool, username, webhook_id).await?;
+    check_url(&req.url, allow_private)?;
+
+    sqlx::query(
+        "UPDATE webhooks SET url = ?, events = ?, secret = ?, active = ?, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?"
+    )
+    .bind(&req.url)
+    .bind(events_column(&req.events))
+    .bind(req.secret.as_ref().unwrap_or(&webhook.secret))
+    .bind(req.active.unwrap_or(webhook.active))
+    .bind(webhook_id)
+    .execute(pool)
+    .await?;
+
+    get(pool, username, webhook_id).await
+}
+
+pub async fn remove(pool: &MySqlPool, username: &str, webhook_id: i32) -> AppResult<()> {
+    let result = sqlx::query("DELETE FROM webhooks WHERE id = ? AND username = ?")
+        .bind(webhook_id)
+        .bind(username)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("webhook is not found".to_string()));
+    }
+
+    Ok(())
+}
+
+// Delivery log of a webhook, newest first
+pub async fn deliveries(
+    pool: &MySqlPool,
+    username: &str,
+    webhook_id: i32,
+    req: WebhookDeliveriesRequest,
+) -> AppResult<WebhookDeliveriesResponse> {
+    find_webhook(pool, username, webhook_id).await?;
+
+    let page = req.page.unwrap_or(1).max(1);
+    let size = req.size.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * size;
+
+    let mut where_clause = "d.webhook_id = ?".to_string();
+    if req.status.is_some() {
+        where_clause.push_str(" AND d.status = ?");
+    }
+
+    let count_query = format!("SELECT COUNT(*) FROM webhook_deliveries d WHERE {}", where_clause);
+    let mut count_query_builder = sqlx::query_as::<_, (i64,)>(&count_query).bind(webhook_id);
+    if let Some(status) = &req.status {
+        count_query_builder = count_query_builder.bind(status);
+    }
+    let total_item = count_query_builder.fetch_one(pool).await?.0;
+
+    let select_query = format!(
+        "SELECT d.id, o.id AS event_id, o.event, o.contact_id, CAST(d.status AS CHAR) AS status, d.attempts,
+                d.response_status, d.error,
+                IF(d.status = 'pending', d.next_attempt_at, NULL) AS next_attempt_at,
+                d.delivered_at, d.created_at
+         FROM webhook_deliveries d
+         JOIN webhook_outbox o ON o.id = d.outbox_id
+         WHERE {}
+         ORDER BY d.id DESC
+         LIMIT ? OFFSET ?",
+        where_clause
+    );
+    let mut query_builder = sqlx::query_as::<_, WebhookDeliveryResponse>(&select_query).bind(webhook_id);
+    if let Some(status) = &req.status {
+        query_builder = query_builder.bind(status);
+    }
+    let data = query_builder.bind(size).bind(offset).fetch_all(pool).await?;
+
+    let total_page = ((total_item as f64) / (size as f64)).ceil() as i32;
+
+    Ok(WebhookDeliveriesResponse {
+        data,
+        paging: PagingResponse {
+            page: Some(page),
+            total_page,
+            total_item,
+        },
+    })
+}
+
+// Tags and custom field values of the member making the change, which the
+// other members' webhooks must not see
+const PRIVATE_FIELDS: [&str; 2] = ["tags", "custom_fields"];
+
+// Queues an event in the transaction of the change it describes, so it is
+// sent exactly when the change is committed. `data` is the contact or
+// address after the change, or before it for deletes.
+pub async fn enqueue<T: Serialize>(
+    conn: &mut MySqlConnection,
+    event: WebhookEvent,
+    contact_id: i32,
+    data: &T,
+) -> AppResult<()> {
+    let mut data = serde_json::to_value(data).map_err(|e| {
+        tracing::error!("{}", e);
+        AppError::Internal
+    })?;
+    if let Some(data) = data.as_object_mut() {
+        for field in PRIVATE_FIELDS {
+            data.remove(field);
+        }
+    }
+    let data = data.to_string();
+
+    sqlx::query(
+        "INSERT INTO webhook_outbox (event, contact_id, address_book_id, data)
+         SELECT ?, id, address_book_id, ? FROM contacts WHERE id = ?"
+    )
+    .bind(event.as_str())
+    .bind(&data)
+    .bind(contact_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// One run of the background dispatcher: queued events become deliveries
+// for every active webhook subscribed to them whose owner can see the
+// contact's address book, then due deliveries are sent.
+pub async fn dispatch(pool: &MySqlPool, client: &reqwest::Client, allow_private: bool) -> AppResult<usize> {
+    fan_out(pool).await?;
+    let sent = deliver_due(pool, client, allow_private).await?;
+    purge(pool).await?;
+
+    Ok(sent)
+}
+
+pub fn http_client(allow_private: bool) -> reqwest::Client {
+    let builder = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(concat!("contacts-webhooks/", env!("CARGO_PKG_VERSION")))
+        // A redirect could lead anywhere, so it counts as a failed attempt
+        .redirect(reqwest::redirect::Policy::none());
+
+    // A proxy would resolve host names itself
+    let builder = if allow_private {
+        builder
+    } else {
+        builder.no_proxy().dns_resolver(Arc::new(PublicResolver))
+    };
+
+    builder.build().expect("webhook HTTP client could not be built")
+}
+
+async fn fan_out(pool: &MySqlPool) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    // Skipped rows are being fanned out by another instance
+    let events: Vec<(i64, String, i32)> = sqlx::query_as(
+        "SELECT id, event, address_book_id FROM webhook_outbox
+         WHERE dispatched_at IS NULL
+         ORDER BY id
+         LIMIT ?
+         FOR UPDATE SKIP LOCKED"
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (outbox_id, event, address_book_id) in events {
+        sqlx::query(
+            "INSERT IGNORE INTO webhook_deliveries (webhook_id, outbox_id)
+             SELECT w.id, ? FROM webhooks w
+             WHERE w.active = TRUE AND FIND_IN_SET(?, w.events) > 0
+               AND w.username IN (SELECT username FROM address_book_members WHERE address_book_id = ?)"
+        )
+        .bind(outbox_id)
+        .bind(&event)
+        .bind(address_book_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE webhook_outbox SET dispatched_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(outbox_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+async fn deliver_due(pool: &MySqlPool, client: &reqwest::Client, allow_private: bool) -> AppResult<usize> {
+    // Claims due deliveries by pushing their next attempt out, so other
+    // dispatchers leave them alone while they are sent
+    let mut tx = pool.begin().await?;
+    let due: Vec<(i64,)> = sqlx::query_as(
+        "SELECT d.id FROM webhook_deliveries d
+         JOIN webhooks w ON w.id = d.webhook_id
+         WHERE d.status = 'pending' AND d.next_attempt_at <= CURRENT_TIMESTAMP AND w.active = TRUE
+         ORDER BY d.next_attempt_at, d.id
+         LIMIT ?
+         FOR UPDATE SKIP LOCKED"
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (delivery_id,) in &due {
+        sqlx::query(
+            "UPDATE webhook_deliveries SET next_attempt_at = CURRENT_TIMESTAMP + INTERVAL ? SECOND WHERE id = ?"
+        )
+        .bind(CLAIM_SECS)
+        .bind(delivery_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    // Sent at once, so the batch finishes well within the claim whatever
+    // the receivers do, and one failing delivery doesn't hold up the rest
+    let mut sends = JoinSet::new();
+    for (delivery_id,) in &due {
+        let (pool, client, delivery_id) = (pool.clone(), client.clone(), *delivery_id);
+        sends.spawn(async move {
+            if let Err(e) = deliver(&pool, &client, allow_private, delivery_id).await {
+                tracing::error!("Could not record webhook delivery {}: {}", delivery_id, e);
+            }
+        });
+    }
+    while sends.join_next().await.is_some() {}
+
+    Ok(due.len())
+}
+
+async fn deliver(
+    pool: &MySqlPool,
+    client: &reqwest::Client,
+    allow_private: bool,
+    delivery_id: i64,
+) -> AppResult<()> {
+    let row = sqlx::query(
+        "SELECT d.attempts, w.url, w.secret, o.id AS event_id, o.event, o.contact_id, o.data, o.created_at
+         FROM webhook_deliveries d
+         JOIN webhooks w ON w.id = d.webhook_id
+         JOIN webhook_outbox o ON o.id = d.outbox_id
+         WHERE d.id = ?"
+    )
+    .bind(delivery_id)
+    .fetch_one(pool)
+    .await?;
+
+    let attempts: i32 = row.try_get::<i32, _>("attempts")? + 1;
+    let url: String = row.try_get("url")?;
+    let secret: String = row.try_get("secret")?;
+    let event_id: i64 = row.try_get("event_id")?;
+    let event: String = row.try_get("event")?;
+    let data: String = row.try_get("data")?;
+
+    let body = serde_json::json!({
+        "id": event_id,
+        "event": event,
+        "contact_id": row.try_get::<i32, _>("contact_id")?,
+        "created_at": row.try_get::<chrono::DateTime<Utc>, _>("created_at")?,
+        "data": serde_json::from_str::<serde_json::Value>(&data).unwrap_or(serde_json::Value::Null),
+    })
+    .to_string();
+
+    let timestamp = Utc::now().timestamp();
+    let result = match check_target(&url, allow_private) {
+        Ok(()) => client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Id", event_id.to_string())
+            .header("X-Webhook-Event", &event)
+            .header("X-Webhook-Timestamp", timestamp.to_string())
+            .header("X-Webhook-Signature", signature(&secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+
+    let (response_status, error) = match result {
+        Ok(response) if response.status().is_success() => {
+            sqlx::query(
+                "UPDATE webhook_deliveries
+                 SET status = 'delivered', attempts = ?, response_status = ?, error = NULL,
+                     delivered_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?"
+            )
+            .bind(attempts)
+            .bind(response.status().as_u16() as i32)
+            .bind(delivery_id)
+            .execute(pool)
+            .await?;
+
+            return Ok(());
+        }
+        Ok(response) => (Some(response.status().as_u16() as i32), format!("HTTP {}", response.status())),
+        Err(e) => (None, e),
+    };
+
+    tracing::warn!("Webhook delivery {} to {} failed: {}", delivery_id, url, error);
+
+    // Retries back off exponentially until the attempts run out
+    let status = if attempts >= MAX_ATTEMPTS { "failed" } else { "pending" };
+    let retry_secs = FIRST_RETRY_SECS << (attempts - 1).min(10);
+    sqlx::query(
+        "UPDATE webhook_deliveries
+         SET status = ?, attempts = ?, response_status = ?, error = ?,
+             next_attempt_at = CURRENT_TIMESTAMP + INTERVAL ? SECOND, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?"
+    )
+    .bind(status)
+    .bind(attempts)
+    .bind(response_status)
+    .bind(error.chars().take(500).collect::<String>())
+    .bind(retry_secs)
+    .bind(delivery_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// `sha256=` and the hex HMAC-SHA256 of `{timestamp}.{body}` keyed with the
+// webhook's secret. Receivers compute the same over the raw body and the
+// `X-Webhook-Timestamp` header, and can reject old timestamps.
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+// Drops old events once none of their deliveries is pending, together with
+// their delivery log
+async fn purge(pool: &MySqlPool) -> AppResult<()> {
+    sqlx::query(
+        "DELETE FROM webhook_outbox
+         WHERE dispatched_at < CURRENT_TIMESTAMP - INTERVAL ? DAY
+           AND NOT EXISTS (
+               SELECT 1 FROM webhook_deliveries d WHERE d.outbox_id = webhook_outbox.id AND d.status = 'pending'
+           )"
+    )
+    .bind(RETENTION_DAYS)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -1,7 +1,151 @@
-use validator::Validate;
-use crate::errors::AppError;
+use std::collections::HashMap;
+use chrono::NaiveDate;
+use serde_json::Value;
+use validator::{Validate, ValidationError};
+use crate::{
+    countries,
+    errors::AppError,
+    models::{CreateAddressRequest, CustomField, CustomFieldType, UpdateAddressRequest},
+};
 
 pub fn validate_request<T: Validate>(data: &T) -> Result<(), AppError> {
     data.validate()
         .map_err(|e| AppError::Validation(e.to_string()))
+}
+
+// Checks a merged patch, reporting only fields `patched` says the patch
+// sets, so values stored before a rule existed don't block unrelated
+// changes. Struct-level errors are reported as `__all__`.
+pub fn validate_patched<T: Validate>(data: &T, patched: impl Fn(&str) -> bool) -> Result<(), AppError> {
+    let Err(mut errors) = data.validate() else {
+        return Ok(());
+    };
+
+    errors.errors_mut().retain(|field, _| patched(field));
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(errors.to_string()))
+    }
+}
+
+pub const CONTACT_LABELS: [&str; 4] = ["work", "home", "mobile", "other"];
+
+pub fn validate_contact_label(label: &str) -> Result<(), ValidationError> {
+    if CONTACT_LABELS.contains(&label) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("label must be one of work, home, mobile, other"))
+    }
+}
+
+pub const ADDRESS_TYPES: [&str; 5] = ["home", "work", "billing", "shipping", "other"];
+
+pub fn validate_address_type(address_type: &str) -> Result<(), ValidationError> {
+    if ADDRESS_TYPES.contains(&address_type) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("type must be one of home, work, billing, shipping, other"))
+    }
+}
+
+// Accepts ISO 3166-1 codes and English country names
+pub fn validate_country(country: &str) -> Result<(), ValidationError> {
+    if countries::find(country).is_some() {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("country is not a known country");
+    if let Some(suggestion) = countries::suggest(country) {
+        error.message = Some(
+            format!("country {} is not known, did you mean {} ({})?", country, suggestion.name, suggestion.code)
+                .into(),
+        );
+    }
+    Err(error)
+}
+
+pub fn validate_create_address(req: &CreateAddressRequest) -> Result<(), ValidationError> {
+    validate_postal_code(&req.country, &req.postal_code)
+}
+
+pub fn validate_update_address(req: &UpdateAddressRequest) -> Result<(), ValidationError> {
+    validate_postal_code(&req.country, &req.postal_code)
+}
+
+fn validate_postal_code(country: &str, postal_code: &str) -> Result<(), ValidationError> {
+    match countries::find(country) {
+        Some(country) if !countries::is_valid_postal_code(country.code, postal_code) => {
+            let mut error = ValidationError::new("postal code is not valid for the country");
+            error.message = Some(format!("postal code is not valid for {}", country.name).into());
+            Err(error)
+        }
+        _ => Ok(()),
+    }
+}
+
+const CUSTOM_TEXT_MAX: usize = 1000;
+
+// Checks custom field values against the user's field definitions and
+// returns them as (field id, stored value) pairs. Null counts as absent.
+pub fn validate_custom_fields(
+    definitions: &[CustomField],
+    values: &HashMap<String, Value>,
+) -> Result<Vec<(i32, String)>, AppError> {
+    if let Some(name) = values
+        .keys()
+        .find(|name| !definitions.iter().any(|field| &field.name == *name))
+    {
+        return Err(AppError::Validation(format!("unknown custom field {}", name)));
+    }
+
+    let mut result = Vec::new();
+    for field in definitions {
+        match values.get(&field.name) {
+            None | Some(Value::Null) if field.required => {
+                return Err(AppError::Validation(format!("custom field {} is required", field.name)));
+            }
+            None | Some(Value::Null) => {}
+            Some(value) => result.push((field.id, stored_custom_value(field, value)?)),
+        }
+    }
+
+    Ok(result)
+}
+
+fn stored_custom_value(field: &CustomField, value: &Value) -> Result<String, AppError> {
+    let kind = field.kind();
+    let stored = match (kind, value) {
+        (CustomFieldType::Text, Value::String(text)) if text.chars().count() <= CUSTOM_TEXT_MAX => {
+            Some(text.clone())
+        }
+        (CustomFieldType::Number, Value::Number(number)) => Some(number.to_string()),
+        (CustomFieldType::Date, Value::String(date))
+            if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() =>
+        {
+            Some(date.clone())
+        }
+        (CustomFieldType::Bool, Value::Bool(flag)) => Some(flag.to_string()),
+        (CustomFieldType::Enum, Value::String(option)) if field.option_list().contains(option) => {
+            Some(option.clone())
+        }
+        _ => None,
+    };
+
+    stored.ok_or_else(|| match kind {
+        CustomFieldType::Enum => AppError::Validation(format!(
+            "custom field {} must be one of {}",
+            field.name,
+            field.option_list().join(", ")
+        )),
+        CustomFieldType::Date => AppError::Validation(format!(
+            "custom field {} must be a date (YYYY-MM-DD)",
+            field.name
+        )),
+        _ => AppError::Validation(format!(
+            "custom field {} must be a {}",
+            field.name,
+            kind.as_str()
+        )),
+    })
 }
\ No newline at end of file
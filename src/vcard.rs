@@ -0,0 +1,399 @@
+use crate::errors::{AppError, AppResult};
+
+// Parsed vCard entry. Only the properties we can map onto contacts and
+// addresses are kept, everything else is ignored.
+#[derive(Debug, Default)]
+pub struct VCard {
+    pub version: Option<String>,
+    pub formatted_name: Option<String>,
+    pub family_name: Option<String>,
+    pub given_name: Option<String>,
+    pub emails: Vec<VCardValue>,
+    pub phones: Vec<VCardValue>,
+    pub addresses: Vec<VCardAddress>,
+}
+
+#[derive(Debug)]
+pub struct VCardValue {
+    pub value: String,
+    pub types: Vec<String>,
+    pub preferred: bool,
+}
+
+#[derive(Debug)]
+pub struct VCardAddress {
+    pub street: Option<String>,
+    pub locality: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+}
+
+// Property parameter as (upper-cased key, value)
+type Param = (String, String);
+
+impl VCard {
+    pub fn display_name(&self) -> Option<String> {
+        if let Some(name) = &self.formatted_name {
+            return Some(name.clone());
+        }
+
+        let parts: Vec<&str> = [&self.given_name, &self.family_name]
+            .into_iter()
+            .flatten()
+            .map(|s| s.as_str())
+            .collect();
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" "))
+        }
+    }
+}
+
+pub fn parse(input: &str) -> AppResult<Vec<VCard>> {
+    let mut cards = Vec::new();
+    let mut current: Option<VCard> = None;
+
+    for line in unfold(input) {
+        let Some((name, params, value)) = split_property(&line) else {
+            continue;
+        };
+
+        match name.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VCARD") => {
+                if current.is_some() {
+                    return Err(AppError::BadRequest("vCard is missing END:VCARD".to_string()));
+                }
+                current = Some(VCard::default());
+            }
+            "END" if value.eq_ignore_ascii_case("VCARD") => {
+                let card = current
+                    .take()
+                    .ok_or(AppError::BadRequest("vCard is missing BEGIN:VCARD".to_string()))?;
+                cards.push(card);
+            }
+            _ => {
+                if let Some(card) = current.as_mut() {
+                    apply_property(card, &name, &params, &value);
+                }
+            }
+        }
+    }
+
+    if current.is_some() {
+        return Err(AppError::BadRequest("vCard is missing END:VCARD".to_string()));
+    }
+
+    if cards.is_empty() {
+        return Err(AppError::BadRequest("no vCard entries found".to_string()));
+    }
+
+    Ok(cards)
+}
+
+fn apply_property(card: &mut VCard, name: &str, params: &[Param], value: &str) {
+    match name {
+        "VERSION" => card.version = Some(value.trim().to_string()),
+        "FN" => card.formatted_name = non_empty(unescape(value)),
+        "N" => {
+            let parts = split_structured(value);
+            card.family_name = parts.first().cloned().and_then(non_empty);
+            card.given_name = parts.get(1).cloned().and_then(non_empty);
+        }
+        "EMAIL" => {
+            if let Some(email) = non_empty(unescape(value)) {
+                card.emails.push(typed_value(email, params));
+            }
+        }
+        "TEL" => {
+            let raw = unescape(value);
+            let phone = raw.strip_prefix("tel:").unwrap_or(&raw).to_string();
+            if let Some(phone) = non_empty(phone) {
+                card.phones.push(typed_value(phone, params));
+            }
+        }
+        "ADR" => {
+            let parts = split_structured(value);
+            let field = |i: usize| parts.get(i).cloned().and_then(non_empty);
+
+            // ADR components: PO box; extended address; street; locality;
+            // region; postal code; country
+            let street: Vec<String> = [field(0), field(1), field(2)]
+                .into_iter()
+                .flatten()
+                .map(|s| s.replace('\n', ", "))
+                .collect();
+
+            card.addresses.push(VCardAddress {
+                street: non_empty(street.join(", ")),
+                locality: field(3),
+                region: field(4),
+                postal_code: field(5),
+                country: field(6),
+            });
+        }
+        _ => {}
+    }
+}
+
+// Joins folded lines (RFC 6350 section 3.2) and drops blank lines.
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for raw in input.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        if let Some(rest) = raw.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        if !raw.trim().is_empty() {
+            lines.push(raw.to_string());
+        }
+    }
+
+    lines
+}
+
+// Splits `group.NAME;PARAM=a,b;PARAM2=c:value` into its name, params and value.
+fn split_property(line: &str) -> Option<(String, Vec<Param>, String)> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        c == ':' && !in_quotes
+    })?;
+
+    let (head, value) = (&line[..colon.0], &line[colon.0 + 1..]);
+    let mut segments = head.split(';');
+    let name = segments.next()?;
+    let name = name.rsplit('.').next().unwrap_or(name).to_ascii_uppercase();
+
+    let params = segments
+        .map(|segment| match segment.split_once('=') {
+            Some((key, value)) => (key.to_ascii_uppercase(), value.trim_matches('"').to_string()),
+            // vCard 2.1 style bare parameter, e.g. `TEL;WORK:...`
+            None => ("TYPE".to_string(), segment.to_string()),
+        })
+        .collect();
+
+    Some((name, params, value.to_string()))
+}
+
+// TYPE=pref (3.0) and PREF=1 (4.0) both mark the preferred value.
+fn typed_value(value: String, params: &[Param]) -> VCardValue {
+    let types: Vec<String> = params
+        .iter()
+        .filter(|(key, _)| key == "TYPE")
+        .flat_map(|(_, value)| value.split(','))
+        .map(|t| t.trim().to_ascii_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let preferred = types.iter().any(|t| t == "pref")
+        || params.iter().any(|(key, value)| key == "PREF" && value.trim() == "1");
+
+    VCardValue { value, types, preferred }
+}
+
+// Splits a structured value (N, ADR) on unescaped semicolons.
+fn split_structured(value: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ';' => parts.push(unescape(&std::mem::take(&mut current))),
+            _ => current.push(c),
+        }
+    }
+    parts.push(unescape(&current));
+
+    parts
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+
+    result
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(body: &str) -> VCard {
+        let input = format!("BEGIN:VCARD\r\nVERSION:4.0\r\n{}END:VCARD\r\n", body);
+        let mut cards = parse(&input).unwrap();
+        assert_eq!(cards.len(), 1);
+        cards.remove(0)
+    }
+
+    fn error(input: &str) -> String {
+        match parse(input) {
+            Err(AppError::BadRequest(message)) => message,
+            other => panic!("expected a bad request for {:?}, got {:?}", input, other),
+        }
+    }
+
+    #[test]
+    fn unfolds_continuation_lines() {
+        let card = read("FN:Budi\r\n  Santoso\r\nEMAIL:budi@exa\r\n\tmple.com\r\n\r\n");
+        assert_eq!(card.formatted_name.as_deref(), Some("Budi Santoso"));
+        assert_eq!(card.emails[0].value, "budi@example.com");
+    }
+
+    #[test]
+    fn accepts_bare_newlines() {
+        let cards = parse("BEGIN:VCARD\nFN:Dewi\nEND:VCARD\n").unwrap();
+        assert_eq!(cards[0].formatted_name.as_deref(), Some("Dewi"));
+    }
+
+    #[test]
+    fn unescapes_values() {
+        let card = read("FN:Budi\\, Jr. \\\\ CEO\\nAcme\r\n");
+        assert_eq!(card.formatted_name.as_deref(), Some("Budi, Jr. \\ CEO\nAcme"));
+    }
+
+    #[test]
+    fn reads_structured_names() {
+        let card = read("N:Santoso;Budi;;;\r\n");
+        assert_eq!(card.family_name.as_deref(), Some("Santoso"));
+        assert_eq!(card.given_name.as_deref(), Some("Budi"));
+        assert_eq!(card.display_name().as_deref(), Some("Budi Santoso"));
+
+        let card = read("N:O\\;Neil;;;;\r\n");
+        assert_eq!(card.family_name.as_deref(), Some("O;Neil"));
+        assert_eq!(card.given_name, None);
+        assert_eq!(card.display_name().as_deref(), Some("O;Neil"));
+    }
+
+    #[test]
+    fn prefers_the_formatted_name() {
+        let card = read("FN:Pak Budi\r\nN:Santoso;Budi;;;\r\n");
+        assert_eq!(card.display_name().as_deref(), Some("Pak Budi"));
+        assert_eq!(read("").display_name(), None);
+    }
+
+    #[test]
+    fn reads_email_types_and_preference() {
+        let card = read(
+            "EMAIL;TYPE=work,pref:budi@acme.com\r\n\
+             EMAIL;TYPE=HOME:budi@example.com\r\n\
+             EMAIL;PREF=1:b@example.com\r\n\
+             EMAIL: \r\n",
+        );
+        assert_eq!(card.emails.len(), 3);
+        assert_eq!(card.emails[0].types, ["work", "pref"]);
+        assert!(card.emails[0].preferred);
+        assert_eq!(card.emails[1].types, ["home"]);
+        assert!(!card.emails[1].preferred);
+        assert!(card.emails[2].preferred);
+    }
+
+    #[test]
+    fn reads_phones_in_every_version() {
+        let card = read(
+            "TEL;VALUE=uri;TYPE=\"voice,cell\":tel:+62-812-3456-789\r\n\
+             TEL;WORK;VOICE:022 123 4567\r\n\
+             item1.TEL:+1 555 0100\r\n",
+        );
+        assert_eq!(card.phones.len(), 3);
+        assert_eq!(card.phones[0].value, "+62-812-3456-789");
+        assert_eq!(card.phones[0].types, ["voice", "cell"]);
+        assert_eq!(card.phones[1].value, "022 123 4567");
+        assert_eq!(card.phones[1].types, ["work", "voice"]);
+        assert_eq!(card.phones[2].value, "+1 555 0100");
+    }
+
+    #[test]
+    fn keeps_colons_in_quoted_parameters() {
+        let card = read("EMAIL;LABEL=\"a:b\";TYPE=work:budi@acme.com\r\n");
+        assert_eq!(card.emails[0].value, "budi@acme.com");
+        assert_eq!(card.emails[0].types, ["work"]);
+    }
+
+    #[test]
+    fn reads_addresses() {
+        let card = read("ADR;TYPE=home:;;Jl. Merdeka 1;Bandung;Jawa Barat;40111;Indonesia\r\n");
+        let address = &card.addresses[0];
+        assert_eq!(address.street.as_deref(), Some("Jl. Merdeka 1"));
+        assert_eq!(address.locality.as_deref(), Some("Bandung"));
+        assert_eq!(address.region.as_deref(), Some("Jawa Barat"));
+        assert_eq!(address.postal_code.as_deref(), Some("40111"));
+        assert_eq!(address.country.as_deref(), Some("Indonesia"));
+    }
+
+    #[test]
+    fn joins_address_street_parts() {
+        let card = read("ADR:PO Box 7;Suite 2;Main St 1\\nBlock B;Springfield\\; East;;;\r\n");
+        let address = &card.addresses[0];
+        assert_eq!(address.street.as_deref(), Some("PO Box 7, Suite 2, Main St 1, Block B"));
+        assert_eq!(address.locality.as_deref(), Some("Springfield; East"));
+        assert_eq!(address.region, None);
+        assert_eq!(address.postal_code, None);
+        assert_eq!(address.country, None);
+    }
+
+    #[test]
+    fn reads_short_addresses() {
+        let card = read("ADR:;;Jl. Merdeka 1\r\n");
+        assert_eq!(card.addresses[0].street.as_deref(), Some("Jl. Merdeka 1"));
+        assert_eq!(card.addresses[0].locality, None);
+    }
+
+    #[test]
+    fn reads_several_cards_and_skips_lines_outside_them() {
+        let cards = parse(
+            "PRODID:export\r\n\
+             BEGIN:VCARD\r\nFN:Budi\r\nEND:VCARD\r\n\
+             FN:Stray\r\n\
+             begin:vcard\r\nfn:Dewi\r\nend:vcard\r\n",
+        )
+        .unwrap();
+        let names: Vec<_> = cards.iter().map(|card| card.formatted_name.as_deref()).collect();
+        assert_eq!(names, [Some("Budi"), Some("Dewi")]);
+    }
+
+    #[test]
+    fn rejects_broken_input() {
+        assert_eq!(error(""), "no vCard entries found");
+        assert_eq!(error("FN:Budi\r\n"), "no vCard entries found");
+        assert_eq!(error("BEGIN:VCARD\r\nFN:Budi\r\n"), "vCard is missing END:VCARD");
+        assert_eq!(error("BEGIN:VCARD\r\nBEGIN:VCARD\r\nEND:VCARD\r\n"), "vCard is missing END:VCARD");
+        assert_eq!(error("FN:Budi\r\nEND:VCARD\r\n"), "vCard is missing BEGIN:VCARD");
+    }
+}